@@ -0,0 +1,262 @@
+use crate::{
+    NorFlashOpError, PartitionBuffer, PartitionEntry, PartitionError, PartitionReaderState,
+    PartitionTable,
+};
+use core::{
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+};
+use embedded_storage_async::nor_flash::{NorFlash, ReadNorFlash};
+
+impl PartitionTable {
+    /// Get async iterator over partitions from table
+    ///
+    /// If `md5` feature isn't enabled `calc_md5` argument will be ignored.
+    pub fn iter_nor_flash_async<'s, S>(
+        &self,
+        storage: &'s mut S,
+        calc_md5: bool,
+    ) -> PartitionNorFlashAsyncIter<'s, S>
+    where
+        S: ReadNorFlash,
+    {
+        PartitionNorFlashAsyncIter {
+            storage,
+            state: PartitionReaderState::new(self.addr, self.size, calc_md5),
+            buffer: MaybeUninit::uninit(),
+        }
+    }
+
+    /// Read partitions from table asynchronously
+    ///
+    /// The entries are read one by one the same way as by [`PartitionTable::read_nor_flash`]
+    /// and appended to `T` which is created using [`Default`].
+    pub async fn read_nor_flash_async<S, T>(
+        &self,
+        storage: &mut S,
+        check_md5: Option<bool>,
+    ) -> Result<T, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+        T: Default + Extend<PartitionEntry>,
+    {
+        let mut iter = self.iter_nor_flash_async(storage, check_md5.is_some());
+        let mut result = T::default();
+
+        while let Some(partition) = iter.next().await {
+            result.extend(Some(partition?));
+        }
+
+        #[cfg(feature = "md5")]
+        if let Some(mandatory_md5) = check_md5 {
+            if !iter.check_md5().unwrap_or(!mandatory_md5) {
+                return Err(PartitionError::InvalidMd5.into());
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Write partitions into table asynchronously
+    ///
+    /// See [`PartitionTable::write_nor_flash`] for details.
+    pub async fn write_nor_flash_async<S>(
+        &self,
+        storage: &mut S,
+        partitions: impl IntoIterator<Item = impl AsRef<PartitionEntry>>,
+        write_md5: bool,
+    ) -> Result<usize, NorFlashOpError<S>>
+    where
+        S: NorFlash,
+    {
+        let mut buffer = MaybeUninit::<[u8; PartitionTable::MAX_SIZE]>::uninit();
+        let buffer = unsafe { buffer.assume_init_mut() };
+
+        self.write_nor_flash_with_buffer_async(storage, buffer, partitions, write_md5)
+            .await
+    }
+
+    /// Write partitions into table asynchronously using specified buffer
+    ///
+    /// See [`PartitionTable::write_nor_flash_with_buffer`] for details.
+    pub async fn write_nor_flash_with_buffer_async<S>(
+        &self,
+        storage: &mut S,
+        buffer: &mut [u8],
+        partitions: impl IntoIterator<Item = impl AsRef<PartitionEntry>>,
+        write_md5: bool,
+    ) -> Result<usize, NorFlashOpError<S>>
+    where
+        S: NorFlash,
+    {
+        let blocks = self.erase_blocks(
+            S::ERASE_SIZE,
+            S::WRITE_SIZE,
+            S::READ_SIZE,
+            storage.capacity(),
+        )?;
+        let buffer = buffer
+            .get_mut(..(blocks.end - blocks.start) as usize)
+            .ok_or(PartitionError::NotEnoughSpace)?;
+
+        storage
+            .read(blocks.start, buffer)
+            .await
+            .map_err(NorFlashOpError::StorageError)?;
+
+        let table_data = &mut buffer[(self.addr - blocks.start) as usize..];
+        let size = self.write_bytes(table_data, partitions, write_md5)?;
+
+        self.check_bytes(&table_data[..size], storage.capacity())?;
+
+        storage
+            .erase(blocks.start, blocks.end)
+            .await
+            .map_err(NorFlashOpError::StorageError)?;
+        storage
+            .write(blocks.start, buffer)
+            .await
+            .map_err(NorFlashOpError::StorageError)?;
+
+        Ok(size)
+    }
+}
+
+/// Async iterator over embedded partition table
+pub struct PartitionNorFlashAsyncIter<'s, S> {
+    storage: &'s mut S,
+    state: PartitionReaderState,
+    buffer: MaybeUninit<PartitionBuffer>,
+}
+
+impl<S> PartitionNorFlashAsyncIter<'_, S>
+where
+    S: ReadNorFlash,
+{
+    /// Read next partition entry
+    pub async fn next_partition(&mut self) -> Result<PartitionEntry, NorFlashOpError<S>> {
+        if self.state.is_done() {
+            return Err(NorFlashOpError::PartitionError(
+                PartitionError::NotEnoughData,
+            ));
+        }
+
+        // Assume that partition data buffer aligned and bigger than S::READ_SIZE
+        if let Err(error) = self
+            .storage
+            .read(self.state.offset(), unsafe {
+                self.buffer.assume_init_mut()
+            })
+            .await
+        {
+            return Err(NorFlashOpError::StorageError(error));
+        }
+
+        self.state
+            .read(unsafe { self.buffer.assume_init_ref() })
+            .map_err(From::from)
+    }
+
+    /// Read next partition entry
+    ///
+    /// Returns `None` when no more entries in table.
+    pub async fn next(&mut self) -> Option<Result<PartitionEntry, NorFlashOpError<S>>> {
+        self.next_partition()
+            .await
+            .map(Some)
+            .or_else(|error| {
+                if matches!(
+                    error,
+                    NorFlashOpError::PartitionError(PartitionError::NotEnoughData)
+                ) {
+                    Ok(None)
+                } else {
+                    Err(error)
+                }
+            })
+            .transpose()
+    }
+}
+
+impl<S> Deref for PartitionNorFlashAsyncIter<'_, S> {
+    type Target = PartitionReaderState;
+
+    fn deref(&self) -> &Self::Target {
+        &self.state
+    }
+}
+
+impl<S> DerefMut for PartitionNorFlashAsyncIter<'_, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.state
+    }
+}
+
+#[cfg(all(test, feature = "md5"))]
+mod test {
+    use crate::*;
+    use core::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+    use embedded_storage_async::nor_flash::ReadNorFlash;
+
+    extern crate std;
+    use std::{sync::Arc, task::Wake, vec, vec::Vec};
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn nor_flash_async() {
+        let data = include_bytes!("../tests/partitions.bin");
+        let mut flash = NorFlashSim::<_>::new(vec![0xff; 0x400000]);
+        flash.data_mut()[0x8000..][..data.len()].copy_from_slice(data);
+
+        let table = PartitionTable::default();
+        let partitions =
+            block_on(table.read_nor_flash_async::<_, Vec<_>>(&mut flash, Some(true))).unwrap();
+        assert_eq!(partitions.len(), 5);
+        assert_eq!(partitions[0].name(), "nvs");
+
+        let mut iter = table.iter_nor_flash_async(&mut flash, true);
+        let mut count = 0;
+        while let Some(partition) = block_on(iter.next()) {
+            assert_eq!(partition.unwrap(), partitions[count]);
+            count += 1;
+        }
+        assert_eq!(count, 5);
+        assert_eq!(iter.check_md5(), Some(true));
+
+        let mut flash = NorFlashSim::<_>::new(vec![0; 0x400000]);
+        let size = block_on(table.write_nor_flash_async(&mut flash, &partitions, true)).unwrap();
+        assert_eq!(size, 5 * PartitionEntry::SIZE);
+        assert_eq!(flash.data()[0x8000..][..data.len()], data[..]);
+        assert!(flash.data()[0x8000 + data.len()..0x9000]
+            .iter()
+            .all(|byte| *byte == 0xff));
+        assert!(flash.data()[..0x8000].iter().all(|byte| *byte == 0));
+        assert!(flash.data()[0x9000..].iter().all(|byte| *byte == 0));
+
+        let mut storage = PartitionStorage::new(&mut flash, partitions[0].clone());
+        assert_eq!(
+            block_on(storage.read(partitions[0].size as u32, &mut [0; 4])),
+            Err(NorFlashOpError::PartitionError(PartitionError::OutOfBounds))
+        );
+    }
+}
@@ -0,0 +1,955 @@
+use crate::{
+    AppPartitionType, BootloaderPartitionType, DataPartitionType, PartitionEntry, PartitionError,
+    PartitionFlags, PartitionTable, PartitionType, TablePartitionType,
+};
+use core::{fmt, str::Lines};
+
+/// CSV parsing error kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsvErrorKind {
+    /// Required field is empty
+    EmptyField,
+
+    /// Numeric value is not a valid
+    InvalidNumber,
+
+    /// Partition type is not a valid
+    InvalidType,
+
+    /// Partition subtype is not a valid
+    InvalidSubType,
+
+    /// Partition flag is not a valid
+    InvalidFlag,
+
+    /// Partition overlaps previous one
+    Overlap,
+
+    /// Value encoding or encoded data is not a valid
+    InvalidEncoding,
+
+    /// Partition specific error
+    PartitionError(PartitionError),
+}
+
+impl From<PartitionError> for CsvErrorKind {
+    fn from(error: PartitionError) -> Self {
+        Self::PartitionError(error)
+    }
+}
+
+impl fmt::Display for CsvErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use CsvErrorKind::*;
+        match self {
+            EmptyField => "Empty field".fmt(f),
+            InvalidNumber => "Invalid number".fmt(f),
+            InvalidType => "Invalid type".fmt(f),
+            InvalidSubType => "Invalid sub type".fmt(f),
+            InvalidFlag => "Invalid flag".fmt(f),
+            Overlap => "Partitions overlap".fmt(f),
+            InvalidEncoding => "Invalid encoding".fmt(f),
+            PartitionError(error) => error.fmt(f),
+        }
+    }
+}
+
+/// CSV parsing error
+///
+/// Both line and column numbers starts from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CsvError {
+    /// Line number
+    pub line: usize,
+
+    /// Column number
+    pub column: usize,
+
+    /// Error kind
+    pub kind: CsvErrorKind,
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.line.fmt(f)?;
+        ":".fmt(f)?;
+        self.column.fmt(f)?;
+        ": ".fmt(f)?;
+        self.kind.fmt(f)
+    }
+}
+
+impl PartitionTable {
+    /// Get iterator over partitions from CSV text
+    ///
+    /// The format is the same as used by `gen_esp32part.py` from ESP-IDF.
+    ///
+    /// Partitions with empty offset will be placed just after the previous one
+    /// (or after the partition table for the first one) with respect to alignment.
+    ///
+    /// Primary bootloader and partition table partitions are not taken into account
+    /// when placing others, the primary partition table with empty offset is placed
+    /// at the address of this table.
+    pub fn iter_csv<'t>(&self, text: &'t str) -> PartitionCsvIter<'t> {
+        PartitionCsvIter {
+            lines: text.lines(),
+            line: 0,
+            count: 0,
+            max_entries: self.data_size() / PartitionEntry::SIZE,
+            table_addr: self.addr,
+            last_end: self.addr + self.size as u32,
+            done: false,
+        }
+    }
+
+    /// Read partitions from CSV text
+    ///
+    /// The partitions can be collected into any container which implements [`FromIterator`],
+    /// like `heapless::Vec` in `no_std` environment or `Vec` when `alloc` is available.
+    pub fn read_csv<T>(&self, text: &str) -> Result<T, CsvError>
+    where
+        T: FromIterator<PartitionEntry>,
+    {
+        self.iter_csv(text).collect()
+    }
+
+    /// Write partitions as CSV text
+    ///
+    /// The format is the same as used by `gen_esp32part.py` from ESP-IDF.
+    ///
+    /// In `minimal` mode the offsets which will be computed automatically when parsing is omitted.
+    ///
+    /// Unknown flags cannot be represented in CSV so will be omitted.
+    pub fn write_csv<W>(
+        &self,
+        out: &mut W,
+        partitions: impl IntoIterator<Item = impl AsRef<PartitionEntry>>,
+        minimal: bool,
+    ) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        out.write_str("# ESP-IDF Partition Table\n")?;
+        out.write_str("# Name, Type, SubType, Offset, Size, Flags\n")?;
+
+        let mut last_end = self.addr + self.size as u32;
+
+        for partition in partitions {
+            let partition = partition.as_ref();
+            let auto_offset = if partition.type_.is_primary() {
+                (partition.type_ == TablePartitionType::Primary.into()).then_some(self.addr)
+            } else {
+                last_end.checked_next_multiple_of(partition.type_.align())
+            };
+
+            write_entry(
+                out,
+                partition,
+                minimal && auto_offset == Some(partition.offset),
+            )?;
+
+            if !partition.type_.is_primary() {
+                last_end = partition.offset.saturating_add(partition.size as u32);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn write_entry(
+    out: &mut impl fmt::Write,
+    partition: &PartitionEntry,
+    skip_offset: bool,
+) -> fmt::Result {
+    out.write_str(partition.name())?;
+    out.write_char(',')?;
+
+    match partition.type_ {
+        PartitionType::App(subtype) => write!(out, "app,{subtype}")?,
+        PartitionType::Data(subtype) => write!(out, "data,{subtype}")?,
+        PartitionType::Bootloader(subtype) => write!(out, "bootloader,{subtype}")?,
+        PartitionType::PartitionTable(subtype) => write!(out, "partition_table,{subtype}")?,
+        PartitionType::User(type_, subtype) => write!(out, "{type_:#04x},{subtype:#04x}")?,
+        PartitionType::Any => write!(out, "0xff,0x00")?,
+    }
+    out.write_char(',')?;
+
+    if !skip_offset {
+        write!(out, "{:#x}", partition.offset)?;
+    }
+    out.write_char(',')?;
+
+    let size = partition.size;
+    if size == 0 {
+        out.write_char('0')?;
+    } else if size % (1 << 20) == 0 {
+        write!(out, "{}M", size >> 20)?;
+    } else if size % (1 << 10) == 0 {
+        write!(out, "{}K", size >> 10)?;
+    } else {
+        write!(out, "{size:#x}")?;
+    }
+    out.write_char(',')?;
+
+    let mut separator = "";
+    for (flag, name) in [
+        (partition.flags.encrypted(), "encrypted"),
+        (partition.flags.readonly(), "readonly"),
+    ] {
+        if flag {
+            out.write_str(separator)?;
+            out.write_str(name)?;
+            separator = ":";
+        }
+    }
+
+    out.write_char('\n')
+}
+
+/// Iterator over partitions from CSV text
+#[derive(Clone)]
+pub struct PartitionCsvIter<'t> {
+    lines: Lines<'t>,
+    line: usize,
+    count: usize,
+    max_entries: usize,
+    table_addr: u32,
+    last_end: u32,
+    done: bool,
+}
+
+impl PartitionCsvIter<'_> {
+    /// Get number of last processed line
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Read next partition entry
+    ///
+    /// Returns `None` when end of text reached.
+    pub fn next_partition(&mut self) -> Result<Option<PartitionEntry>, CsvError> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let result = self.read_partition();
+
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+
+        result
+    }
+
+    fn read_partition(&mut self) -> Result<Option<PartitionEntry>, CsvError> {
+        loop {
+            let Some(line) = self.lines.next() else {
+                return Ok(None);
+            };
+            self.line += 1;
+
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = Fields::new(line, self.line);
+
+            if self.count >= self.max_entries {
+                return Err(fields.error(PartitionError::TooManyData));
+            }
+
+            let (mut entry, offset, offset_error) = parse_entry(&mut fields)?;
+
+            let offset = match offset {
+                Some(offset) if entry.type_.is_primary() => offset,
+                Some(offset) if offset < self.last_end => {
+                    return Err(CsvError {
+                        kind: CsvErrorKind::Overlap,
+                        ..offset_error
+                    })
+                }
+                Some(offset) => offset,
+                None if entry.type_ == TablePartitionType::Primary.into() => self.table_addr,
+                None if entry.type_.is_primary() => {
+                    return Err(CsvError {
+                        kind: CsvErrorKind::EmptyField,
+                        ..offset_error
+                    })
+                }
+                None => {
+                    let align = entry.type_.align();
+                    self.last_end
+                        .checked_next_multiple_of(align)
+                        .ok_or(CsvError {
+                            kind: CsvErrorKind::InvalidNumber,
+                            ..offset_error
+                        })?
+                }
+            };
+            entry.set_offset(offset).map_err(|_| offset_error)?;
+
+            let end = offset.checked_add(entry.size as u32).ok_or(CsvError {
+                kind: CsvErrorKind::InvalidNumber,
+                ..offset_error
+            })?;
+            if !entry.type_.is_primary() {
+                self.last_end = end;
+            }
+            self.count += 1;
+
+            return Ok(Some(entry));
+        }
+    }
+}
+
+impl Iterator for PartitionCsvIter<'_> {
+    type Item = Result<PartitionEntry, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_partition().transpose()
+    }
+}
+
+/// Splitter of CSV line into fields which tracks columns
+pub(crate) struct Fields<'t> {
+    line: &'t str,
+    rest: Option<&'t str>,
+    line_no: usize,
+    column: usize,
+    next_column: usize,
+}
+
+impl<'t> Fields<'t> {
+    pub(crate) fn new(line: &'t str, line_no: usize) -> Self {
+        Self {
+            line,
+            rest: Some(line),
+            line_no,
+            column: 1,
+            next_column: 1,
+        }
+    }
+
+    /// Get next trimmed field
+    ///
+    /// Missing fields treated as empty.
+    pub(crate) fn next(&mut self) -> &'t str {
+        let Some(rest) = self.rest else {
+            self.column = self.line.len() + 1;
+            return "";
+        };
+
+        let (field, rest) = match rest.split_once(',') {
+            Some((field, rest)) => (field, Some(rest)),
+            None => (rest, None),
+        };
+        self.rest = rest;
+
+        let trimmed = field.trim_start();
+        self.column = self.next_column + (field.len() - trimmed.len());
+        self.next_column += field.len() + 1;
+
+        trimmed.trim_end()
+    }
+
+    /// Create error which points to the last field
+    pub(crate) fn error(&self, kind: impl Into<CsvErrorKind>) -> CsvError {
+        CsvError {
+            line: self.line_no,
+            column: self.column,
+            kind: kind.into(),
+        }
+    }
+}
+
+/// Parse partition entry with optional offset
+///
+/// The entry offset is not set here because it depends on previous entries.
+fn parse_entry(fields: &mut Fields) -> Result<(PartitionEntry, Option<u32>, CsvError), CsvError> {
+    let name = fields.next();
+    let name_error = fields.error(PartitionError::InvalidString);
+
+    let raw_type = parse_type(fields.next()).map_err(|kind| fields.error(kind))?;
+    let type_error = fields.error(CsvErrorKind::InvalidType);
+
+    let raw_subtype = parse_subtype(raw_type, fields.next()).map_err(|kind| fields.error(kind))?;
+    let type_ =
+        PartitionType::from_bytes_lenient(&[raw_type, raw_subtype]).map_err(
+            |error| match error {
+                PartitionError::InvalidType(_) | PartitionError::InvalidUserType(_) => type_error,
+                _ => fields.error(CsvErrorKind::InvalidSubType),
+            },
+        )?;
+
+    let offset = fields.next();
+    let offset = if offset.is_empty() {
+        None
+    } else {
+        Some(parse_int(offset).map_err(|kind| fields.error(kind))?)
+    };
+    let offset_error = fields.error(PartitionError::InvalidAlignment);
+
+    let size = fields.next();
+    if size.is_empty() {
+        return Err(fields.error(CsvErrorKind::EmptyField));
+    }
+    let size = parse_int(size).map_err(|kind| fields.error(kind))?;
+
+    let flags = parse_flags(fields.next()).map_err(|kind| fields.error(kind))?;
+
+    let entry =
+        PartitionEntry::new(type_, 0, size as usize, name, flags).map_err(|_| name_error)?;
+
+    Ok((entry, offset, offset_error))
+}
+
+pub(crate) fn parse_type(field: &str) -> Result<u8, CsvErrorKind> {
+    if field.is_empty() {
+        return Err(CsvErrorKind::EmptyField);
+    }
+
+    if let Some(raw) = PartitionType::type_from_name(field) {
+        return Ok(raw);
+    }
+
+    parse_int(field)
+        .ok()
+        .and_then(|raw| raw.try_into().ok())
+        .ok_or(CsvErrorKind::InvalidType)
+}
+
+pub(crate) fn parse_subtype(raw_type: u8, field: &str) -> Result<u8, CsvErrorKind> {
+    let subtype = match raw_type {
+        0x01 if field.is_empty() => Some(DataPartitionType::Undefined.into()),
+        0x00 | 0x02 | 0x03 if field.is_empty() => return Err(CsvErrorKind::EmptyField),
+        0x00 => AppPartitionType::from_name(field).and_then(|ty| ty.try_into().ok()),
+        0x01 => DataPartitionType::from_name(field).map(u8::from),
+        0x02 => BootloaderPartitionType::from_name(field).map(u8::from),
+        0x03 => TablePartitionType::from_name(field).map(u8::from),
+        _ => None,
+    };
+
+    if let Some(subtype) = subtype {
+        return Ok(subtype);
+    }
+
+    parse_int(field)
+        .ok()
+        .and_then(|raw| raw.try_into().ok())
+        .ok_or(CsvErrorKind::InvalidSubType)
+}
+
+fn parse_flags(field: &str) -> Result<PartitionFlags, CsvErrorKind> {
+    let mut flags = PartitionFlags::NONE;
+
+    for flag in field.split(':').map(str::trim) {
+        match flag {
+            "encrypted" => flags.set_encrypted(true),
+            "readonly" => flags.set_readonly(true),
+            "" => {}
+            _ => return Err(CsvErrorKind::InvalidFlag),
+        }
+    }
+
+    Ok(flags)
+}
+
+/// Parse integer like Python's `int(value, 0)` with optional `K` or `M` suffix
+fn parse_int(field: &str) -> Result<u32, CsvErrorKind> {
+    let (field, multiplier) = match field.as_bytes().last() {
+        Some(b'k' | b'K') => (&field[..field.len() - 1], 1 << 10),
+        Some(b'm' | b'M') => (&field[..field.len() - 1], 1 << 20),
+        _ => (field, 1),
+    };
+
+    let prefix = field.get(..2);
+    let (digits, radix) = if prefix.is_some_and(|p| p.eq_ignore_ascii_case("0x")) {
+        (&field[2..], 16)
+    } else if prefix.is_some_and(|p| p.eq_ignore_ascii_case("0o")) {
+        (&field[2..], 8)
+    } else if prefix.is_some_and(|p| p.eq_ignore_ascii_case("0b")) {
+        (&field[2..], 2)
+    } else if field.len() > 1 && field.starts_with('0') {
+        // Python does not allow leading zeros in decimal numbers
+        return Err(CsvErrorKind::InvalidNumber);
+    } else {
+        (field, 10)
+    };
+
+    // Unlike Python, Rust allows leading sign which isn't expected here
+    if digits.starts_with('+') {
+        return Err(CsvErrorKind::InvalidNumber);
+    }
+
+    u32::from_str_radix(digits, radix)
+        .ok()
+        .and_then(|value| value.checked_mul(multiplier))
+        .ok_or(CsvErrorKind::InvalidNumber)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    const CSV: &str = "# Name,   Type, SubType, Offset,  Size, Flags
+nvs,      data, nvs,     0x9000,   0x4000,
+otadata,  data, ota,     0xd000,   0x2000
+phy_init, data, phy,     ,         4K,
+factory,  App,  Factory, ,         1M,     encrypted
+ota_15,   app,  ota_15,  0x110000, 1M,
+test,     app,  0x20,    ,         0x100000,
+storage,  data, ,        4M,       64K,
+custom,   0x40, 0x7,     ,         4096,
+secure,   data, 0x3f,    ,         8K,
+";
+
+    #[test]
+    fn parse_partitions() {
+        let table = PartitionTable::default();
+        let mut iter = table.iter_csv(CSV);
+
+        let part = iter.next().unwrap().unwrap();
+        assert_eq!(part.type_, PartitionType::Data(DataPartitionType::Nvs));
+        assert_eq!(part.offset, 0x9000);
+        assert_eq!(part.size, 0x4000);
+        assert_eq!(part.name(), "nvs");
+        assert_eq!(part.flags, PartitionFlags::NONE);
+
+        let part = iter.next().unwrap().unwrap();
+        assert_eq!(part.type_, PartitionType::Data(DataPartitionType::Ota));
+        assert_eq!(part.offset, 0xd000);
+        assert_eq!(part.size, 0x2000);
+        assert_eq!(part.name(), "otadata");
+
+        let part = iter.next().unwrap().unwrap();
+        assert_eq!(part.type_, PartitionType::Data(DataPartitionType::Phy));
+        assert_eq!(part.offset, 0xf000);
+        assert_eq!(part.size, 4 << 10);
+
+        let part = iter.next().unwrap().unwrap();
+        assert_eq!(part.type_, PartitionType::App(AppPartitionType::Factory));
+        assert_eq!(part.offset, 0x10000);
+        assert_eq!(part.size, 1 << 20);
+        assert!(part.flags.encrypted());
+        assert!(!part.flags.readonly());
+
+        let part = iter.next().unwrap().unwrap();
+        assert_eq!(part.type_, PartitionType::App(AppPartitionType::Ota(15)));
+        assert_eq!(part.offset, 0x110000);
+
+        let part = iter.next().unwrap().unwrap();
+        assert_eq!(part.type_, PartitionType::App(AppPartitionType::Test));
+        assert_eq!(part.offset, 0x210000);
+        assert_eq!(part.size, 1 << 20);
+
+        let part = iter.next().unwrap().unwrap();
+        assert_eq!(
+            part.type_,
+            PartitionType::Data(DataPartitionType::Undefined)
+        );
+        assert_eq!(part.offset, 4 << 20);
+
+        let part = iter.next().unwrap().unwrap();
+        assert_eq!(part.type_, PartitionType::User(0x40, 0x07));
+        assert_eq!(part.offset, 0x410000);
+        assert_eq!(part.size, 4096);
+
+        let part = iter.next().unwrap().unwrap();
+        assert_eq!(
+            part.type_,
+            PartitionType::Data(DataPartitionType::Other(0x3f))
+        );
+        assert_eq!(part.offset, 0x411000);
+
+        assert!(iter.next().is_none());
+        assert_eq!(iter.line(), 10);
+
+        #[cfg(feature = "heapless")]
+        {
+            let parts: heapless::Vec<PartitionEntry, 9> = table.read_csv(CSV).unwrap();
+            assert_eq!(parts.len(), 9);
+            assert_eq!(parts[7].name(), "custom");
+        }
+    }
+
+    fn parse_fixture(csv: &str, bin: &[u8]) {
+        let table = PartitionTable::default();
+        let mut reader = PartitionReaderState::new(0, bin.len(), true);
+        let mut count = 0;
+
+        for (csv_part, bin_part) in table
+            .iter_csv(csv)
+            .zip(bin.chunks_exact(PartitionEntry::SIZE))
+        {
+            let csv_part = csv_part.unwrap();
+            let bin_part = reader.read(bin_part.try_into().unwrap()).unwrap();
+            assert_eq!(csv_part, bin_part);
+            count += 1;
+        }
+
+        assert_eq!(count, table.iter_csv(csv).count());
+        assert!(matches!(
+            reader.read(
+                bin[count * PartitionEntry::SIZE..][..PartitionEntry::SIZE]
+                    .try_into()
+                    .unwrap()
+            ),
+            Err(PartitionError::NotEnoughData)
+        ));
+    }
+
+    #[test]
+    fn parse_partitions_fixture() {
+        parse_fixture(
+            include_str!("../tests/partitions.csv"),
+            include_bytes!("../tests/partitions.bin"),
+        );
+    }
+
+    #[test]
+    fn parse_partitions_ota_fixture() {
+        parse_fixture(
+            include_str!("../tests/partitions-ota.csv"),
+            include_bytes!("../tests/partitions-ota.bin"),
+        );
+    }
+
+    /// Fixed-capacity text buffer
+    struct Text {
+        data: [u8; 4096],
+        len: usize,
+    }
+
+    impl Text {
+        fn new() -> Self {
+            Self {
+                data: [0; 4096],
+                len: 0,
+            }
+        }
+
+        fn as_str(&self) -> &str {
+            core::str::from_utf8(&self.data[..self.len]).unwrap()
+        }
+    }
+
+    impl core::fmt::Write for Text {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            let data = self
+                .data
+                .get_mut(self.len..self.len + s.len())
+                .ok_or(core::fmt::Error)?;
+            data.copy_from_slice(s.as_bytes());
+            self.len += s.len();
+            Ok(())
+        }
+    }
+
+    fn write_fixture(bin: &[u8], minimal: bool, csv: &str) {
+        let table = PartitionTable::default();
+        let parts = bin
+            .chunks_exact(PartitionEntry::SIZE)
+            .map_while(|part| PartitionEntry::try_from(part).ok());
+
+        let mut text = Text::new();
+        table.write_csv(&mut text, parts.clone(), minimal).unwrap();
+        assert_eq!(text.as_str(), csv);
+
+        for (csv_part, bin_part) in table.iter_csv(text.as_str()).zip(parts) {
+            assert_eq!(csv_part.unwrap(), bin_part);
+        }
+    }
+
+    #[test]
+    fn parse_partitions_limit() {
+        use core::fmt::Write;
+
+        let table = PartitionTable::default();
+        let max_entries = table.data_size() / PartitionEntry::SIZE;
+        let mut text = Text::new();
+
+        for _ in 0..max_entries {
+            text.write_str("nvs,data,nvs,,4K,\n").unwrap();
+        }
+        assert_eq!(table.iter_csv(text.as_str()).count(), max_entries);
+        assert!(table.iter_csv(text.as_str()).all(|part| part.is_ok()));
+
+        text.write_str("nvs,data,nvs,,4K,\n").unwrap();
+        assert_eq!(
+            table.iter_csv(text.as_str()).find_map(Result::err),
+            Some(CsvError {
+                line: max_entries + 1,
+                column: 1,
+                kind: PartitionError::TooManyData.into(),
+            })
+        );
+    }
+
+    #[test]
+    fn write_partitions() {
+        write_fixture(
+            include_bytes!("../tests/partitions.bin"),
+            false,
+            "# ESP-IDF Partition Table
+# Name, Type, SubType, Offset, Size, Flags
+nvs,data,nvs,0x9000,24K,
+phy_init,data,phy,0xf000,4K,
+factory,app,factory,0x10000,3M,
+coredump,data,coredump,0x310000,64K,
+nvs_ext,data,nvs,0x320000,64K,
+",
+        );
+    }
+
+    #[test]
+    fn write_partitions_ota_minimal() {
+        write_fixture(
+            include_bytes!("../tests/partitions-ota.bin"),
+            true,
+            "# ESP-IDF Partition Table
+# Name, Type, SubType, Offset, Size, Flags
+nvs,data,nvs,,16K,
+otadata,data,ota,,8K,
+phy_init,data,phy,,4K,
+factory,app,factory,,1M,
+ota_0,app,ota_0,,1M,
+ota_1,app,ota_1,,1M,
+coredump,data,coredump,,64K,
+nvs_ext,data,nvs,,64K,
+",
+        );
+    }
+
+    #[test]
+    fn write_partitions_custom() {
+        let table = PartitionTable::default();
+        let parts = [
+            PartitionEntry::new(
+                DataPartitionType::Nvs,
+                0x9000,
+                0x1880,
+                "nvs",
+                PartitionFlags::ENCRYPTED | PartitionFlags::READONLY,
+            )
+            .unwrap(),
+            PartitionEntry::new(AppPartitionType::Test, 0x20000, 1 << 20, "test", true).unwrap(),
+            PartitionEntry::new(
+                PartitionType::User(0x40, 0x07),
+                0x120000,
+                4096,
+                "custom",
+                PartitionFlags::READONLY,
+            )
+            .unwrap(),
+            PartitionEntry::new(
+                AppPartitionType::Other(0x30),
+                0x130000,
+                1 << 20,
+                "app",
+                false,
+            )
+            .unwrap(),
+            PartitionEntry::new(DataPartitionType::Undefined, 0x230000, 0, "empty", false).unwrap(),
+        ];
+
+        let mut text = Text::new();
+        table.write_csv(&mut text, &parts, true).unwrap();
+        assert_eq!(
+            text.as_str(),
+            "# ESP-IDF Partition Table
+# Name, Type, SubType, Offset, Size, Flags
+nvs,data,nvs,,0x1880,encrypted:readonly
+test,app,test,0x20000,1M,encrypted
+custom,0x40,0x07,,4K,readonly
+app,app,0x30,,1M,
+empty,data,undefined,,0,
+"
+        );
+
+        for (csv_part, part) in table.iter_csv(text.as_str()).zip(&parts) {
+            assert_eq!(&csv_part.unwrap(), part);
+        }
+    }
+
+    #[test]
+    fn system_partitions() {
+        let table = PartitionTable::default();
+        let csv = "# ESP-IDF Partition Table
+# Name, Type, SubType, Offset, Size, Flags
+bootloader,bootloader,primary,0x1000,28K,
+partition_table,partition_table,primary,,4K,
+nvs,data,nvs,,16K,
+factory,app,factory,,1M,
+recovery,bootloader,recovery,,28K,
+pt_ota,partition_table,ota,,4K,
+";
+
+        let parts: [PartitionEntry; 6] = core::array::from_fn({
+            let mut iter = table.iter_csv(csv);
+            move |_| iter.next().unwrap().unwrap()
+        });
+
+        assert_eq!(
+            parts[0].type_,
+            PartitionType::Bootloader(BootloaderPartitionType::Primary)
+        );
+        assert_eq!(parts[0].offset, 0x1000);
+        assert_eq!(
+            parts[1].type_,
+            PartitionType::PartitionTable(TablePartitionType::Primary)
+        );
+        assert_eq!(parts[1].offset, 0x8000);
+        assert_eq!(parts[2].offset, 0x9000);
+        assert_eq!(parts[3].offset, 0x10000);
+        assert_eq!(
+            parts[4].type_,
+            PartitionType::Bootloader(BootloaderPartitionType::Recovery)
+        );
+        assert_eq!(parts[4].offset, 0x110000);
+        assert_eq!(
+            parts[5].type_,
+            PartitionType::PartitionTable(TablePartitionType::Ota)
+        );
+        assert_eq!(parts[5].offset, 0x117000);
+
+        for part in &parts {
+            let mut data = [0; PartitionEntry::SIZE];
+            part.to_bytes(&mut data).unwrap();
+            assert_eq!(&PartitionEntry::from_bytes(&data).unwrap(), part);
+        }
+        assert_eq!(table.check(&parts, 4 << 20), Ok(()));
+
+        let mut text = Text::new();
+        table.write_csv(&mut text, &parts, true).unwrap();
+        assert_eq!(text.as_str(), csv);
+
+        assert_eq!(
+            parse_error("bootloader, bootloader, primary, , 28K"),
+            CsvError {
+                line: 1,
+                column: 34,
+                kind: CsvErrorKind::EmptyField,
+            }
+        );
+
+        assert_eq!(
+            parse_error("bootloader, bootloader, secondary, 0x1000, 28K"),
+            CsvError {
+                line: 1,
+                column: 25,
+                kind: CsvErrorKind::InvalidSubType,
+            }
+        );
+    }
+
+    fn parse_error(csv: &str) -> CsvError {
+        PartitionTable::default()
+            .iter_csv(csv)
+            .find_map(Result::err)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse_error("# comment\nnvs, data, nvs, 0x9000, 0x4000\nfoo, bar, nvs, 0xd000, 4K"),
+            CsvError {
+                line: 3,
+                column: 6,
+                kind: CsvErrorKind::InvalidType,
+            }
+        );
+
+        assert_eq!(
+            parse_error("reserved, 0x04, 0x00, 0x1000, 28K"),
+            CsvError {
+                line: 1,
+                column: 11,
+                kind: CsvErrorKind::InvalidType,
+            }
+        );
+
+        assert_eq!(
+            parse_error("nvs, data, nvz, 0x9000, 0x4000"),
+            CsvError {
+                line: 1,
+                column: 12,
+                kind: CsvErrorKind::InvalidSubType,
+            }
+        );
+
+        assert_eq!(
+            parse_error("factory, app, , 0x10000, 1M"),
+            CsvError {
+                line: 1,
+                column: 15,
+                kind: CsvErrorKind::EmptyField,
+            }
+        );
+
+        assert_eq!(
+            parse_error("nvs, data, nvs, 09000, 0x4000"),
+            CsvError {
+                line: 1,
+                column: 17,
+                kind: CsvErrorKind::InvalidNumber,
+            }
+        );
+
+        assert_eq!(
+            parse_error("factory, app, factory, 0x11000, 1M"),
+            CsvError {
+                line: 1,
+                column: 24,
+                kind: PartitionError::InvalidAlignment.into(),
+            }
+        );
+
+        assert_eq!(
+            parse_error("nvs, data, nvs, 0x8000, 0x4000"),
+            CsvError {
+                line: 1,
+                column: 17,
+                kind: CsvErrorKind::Overlap,
+            }
+        );
+
+        assert_eq!(
+            parse_error("nvs, data, nvs, , 0x4000\nphy, data, phy, 0xc000, 4K"),
+            CsvError {
+                line: 2,
+                column: 17,
+                kind: CsvErrorKind::Overlap,
+            }
+        );
+
+        assert_eq!(
+            parse_error("nvs, data, nvs, 0x9000"),
+            CsvError {
+                line: 1,
+                column: 23,
+                kind: CsvErrorKind::EmptyField,
+            }
+        );
+
+        assert_eq!(
+            parse_error("nvs, data, nvs, 0x9000, 16K, encrypted:secret"),
+            CsvError {
+                line: 1,
+                column: 30,
+                kind: CsvErrorKind::InvalidFlag,
+            }
+        );
+
+        assert_eq!(
+            parse_error("very_long_partition_name, data, nvs, 0x9000, 16K"),
+            CsvError {
+                line: 1,
+                column: 1,
+                kind: PartitionError::InvalidString.into(),
+            }
+        );
+    }
+}
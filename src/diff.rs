@@ -0,0 +1,572 @@
+use crate::{PartitionEntry, PartitionTable};
+use core::fmt;
+
+/// Partition change kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Partition is present only in new layout
+    Added,
+
+    /// Partition is present only in old layout
+    Removed,
+
+    /// Partition name is changed
+    Renamed,
+
+    /// Partition type or subtype is changed
+    Retyped,
+
+    /// Partition offset is changed
+    Moved,
+
+    /// Partition size is changed
+    Resized,
+
+    /// Partition flags are changed
+    FlagsChanged,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ChangeKind::*;
+        match self {
+            Added => "Added",
+            Removed => "Removed",
+            Renamed => "Renamed",
+            Retyped => "Type changed",
+            Moved => "Moved",
+            Resized => "Resized",
+            FlagsChanged => "Flags changed",
+        }
+        .fmt(f)
+    }
+}
+
+/// Partition change between two layouts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartitionChange {
+    /// Index of partition in old layout
+    pub old: Option<usize>,
+
+    /// Index of partition in new layout
+    pub new: Option<usize>,
+
+    /// Change kind
+    pub kind: ChangeKind,
+}
+
+impl fmt::Display for PartitionChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "Partition".fmt(f)?;
+        if let Some(old) = self.old {
+            " #".fmt(f)?;
+            old.fmt(f)?;
+        }
+        if self.old.is_some() && self.new.is_some() {
+            " ->".fmt(f)?;
+        }
+        if let Some(new) = self.new {
+            " #".fmt(f)?;
+            new.fmt(f)?;
+        }
+        ": ".fmt(f)?;
+        self.kind.fmt(f)
+    }
+}
+
+/// Flash region which belongs to different partition in new layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RangeChange {
+    /// Region offset
+    pub offset: u32,
+
+    /// Region size
+    pub size: usize,
+
+    /// Index of partition which owned region in old layout
+    pub old: Option<usize>,
+
+    /// Index of partition which owns region in new layout
+    pub new: Option<usize>,
+}
+
+/// Difference between two partition layouts
+///
+/// Partitions are matched by name. The partitions which have no counterpart with the same name
+/// are matched by type and subtype in order of appearance.
+/// Only first [`PartitionTable::MAX_ENTRIES`] partitions of each layout can be matched.
+#[derive(Clone, Debug)]
+pub struct PartitionDiff<O, N> {
+    old: O,
+    new: N,
+    old_to_new: Matches,
+    new_to_old: Matches,
+}
+
+/// Indexes of counterparts in other layout
+type Matches = [Option<u8>; PartitionTable::MAX_ENTRIES];
+
+impl<O, N> PartitionDiff<O, N>
+where
+    O: Iterator + Clone,
+    O::Item: AsRef<PartitionEntry>,
+    N: Iterator + Clone,
+    N::Item: AsRef<PartitionEntry>,
+{
+    /// Compare old and new partitions
+    pub fn new<OI, NI>(old: OI, new: NI) -> Self
+    where
+        OI: IntoIterator<IntoIter = O>,
+        NI: IntoIterator<IntoIter = N>,
+    {
+        let (old, new) = (old.into_iter(), new.into_iter());
+
+        let old_named = match_names(old.clone(), new.clone());
+        let new_named = match_names(new.clone(), old.clone());
+
+        Self {
+            old_to_new: match_types(old.clone(), new.clone(), &old_named, &new_named),
+            new_to_old: match_types(new.clone(), old.clone(), &new_named, &old_named),
+            old,
+            new,
+        }
+    }
+
+    /// Get index of old partition which corresponds to new partition with specified index
+    pub fn old_index(&self, new_index: usize) -> Option<usize> {
+        self.new_to_old.get(new_index).copied()?.map(usize::from)
+    }
+
+    /// Get index of new partition which corresponds to old partition with specified index
+    pub fn new_index(&self, old_index: usize) -> Option<usize> {
+        self.old_to_new.get(old_index).copied()?.map(usize::from)
+    }
+
+    /// Get iterator over partition changes
+    ///
+    /// Changes of new partitions are reported first in order of appearance
+    /// followed by removed partitions.
+    pub fn changes(&self) -> PartitionChangeIter<O, N> {
+        PartitionChangeIter {
+            diff: self.clone(),
+            index: 0,
+            check: Check::Match,
+        }
+    }
+
+    /// Get iterator over flash regions which belong to different partition now
+    ///
+    /// The region is reported when its owner in new layout isn't the counterpart
+    /// of its owner in old layout including regions which become free or occupied.
+    pub fn range_changes(&self) -> RangeChangeIter<O, N> {
+        RangeChangeIter {
+            diff: self.clone(),
+            offset: 0,
+        }
+    }
+
+    fn owners(&self, offset: u64) -> (Option<usize>, Option<usize>) {
+        (
+            owner(self.old.clone(), offset),
+            owner(self.new.clone(), offset),
+        )
+    }
+
+    fn next_boundary(&self, offset: u64) -> Option<u64> {
+        let old = self.old.clone().map(|partition| bounds(partition.as_ref()));
+        let new = self.new.clone().map(|partition| bounds(partition.as_ref()));
+
+        old.chain(new)
+            .flat_map(|(start, end)| [start, end])
+            .filter(|boundary| *boundary > offset)
+            .min()
+    }
+}
+
+fn bounds(partition: &PartitionEntry) -> (u64, u64) {
+    let offset = partition.offset as u64;
+    (offset, offset + partition.size as u64)
+}
+
+fn owner<I>(mut partitions: I, offset: u64) -> Option<usize>
+where
+    I: Iterator,
+    I::Item: AsRef<PartitionEntry>,
+{
+    partitions.position(|partition| {
+        let (start, end) = bounds(partition.as_ref());
+        start <= offset && offset < end
+    })
+}
+
+fn find_name<I>(mut partitions: I, name: &str) -> Option<usize>
+where
+    I: Iterator,
+    I::Item: AsRef<PartitionEntry>,
+{
+    partitions.position(|partition| partition.as_ref().name() == name)
+}
+
+/// Find counterparts with the same names
+fn match_names<A, B>(this: A, other: B) -> Matches
+where
+    A: Iterator,
+    A::Item: AsRef<PartitionEntry>,
+    B: Iterator + Clone,
+    B::Item: AsRef<PartitionEntry>,
+{
+    let mut matches = [None; PartitionTable::MAX_ENTRIES];
+    for (found, partition) in matches.iter_mut().zip(this) {
+        *found = find_name(
+            other.clone().take(PartitionTable::MAX_ENTRIES),
+            partition.as_ref().name(),
+        )
+        .map(|index| index as u8);
+    }
+    matches
+}
+
+/// Find counterparts by type and subtype for partitions which have no ones with the same names
+///
+/// The n-th unmatched partition of some type in one layout corresponds to
+/// the n-th unmatched partition of the same type in another one.
+fn match_types<A, B>(this: A, other: B, this_named: &Matches, other_named: &Matches) -> Matches
+where
+    A: Iterator + Clone,
+    A::Item: AsRef<PartitionEntry>,
+    B: Iterator + Clone,
+    B::Item: AsRef<PartitionEntry>,
+{
+    let mut matches = *this_named;
+    for (index, partition) in this.clone().take(PartitionTable::MAX_ENTRIES).enumerate() {
+        if matches[index].is_some() {
+            continue;
+        }
+        let partition = partition.as_ref();
+
+        let rank = this
+            .clone()
+            .zip(this_named)
+            .take(index)
+            .filter(|(candidate, named)| {
+                named.is_none() && candidate.as_ref().type_ == partition.type_
+            })
+            .count();
+
+        matches[index] = other
+            .clone()
+            .zip(other_named)
+            .enumerate()
+            .filter(|(_, (candidate, named))| {
+                named.is_none() && candidate.as_ref().type_ == partition.type_
+            })
+            .nth(rank)
+            .map(|(index, _)| index as u8);
+    }
+    matches
+}
+
+#[derive(Clone, Copy)]
+enum Check {
+    Match,
+    Name,
+    Type,
+    Offset,
+    Size,
+    Flags,
+    Removed,
+}
+
+/// Iterator over partition changes
+pub struct PartitionChangeIter<O, N> {
+    diff: PartitionDiff<O, N>,
+    index: usize,
+    check: Check,
+}
+
+impl<O, N> Iterator for PartitionChangeIter<O, N>
+where
+    O: Iterator + Clone,
+    O::Item: AsRef<PartitionEntry>,
+    N: Iterator + Clone,
+    N::Item: AsRef<PartitionEntry>,
+{
+    type Item = PartitionChange;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Check::Removed = self.check {
+                self.diff.old.clone().nth(self.index)?;
+                let index = self.index;
+                self.index += 1;
+                if self.diff.new_index(index).is_none() {
+                    return Some(PartitionChange {
+                        old: Some(index),
+                        new: None,
+                        kind: ChangeKind::Removed,
+                    });
+                }
+                continue;
+            }
+
+            let Some(new) = self.diff.new.clone().nth(self.index) else {
+                self.index = 0;
+                self.check = Check::Removed;
+                continue;
+            };
+            let new = new.as_ref();
+            let new_index = self.index;
+
+            let Some(old_index) = self.diff.old_index(new_index) else {
+                self.index += 1;
+                return Some(PartitionChange {
+                    old: None,
+                    new: Some(new_index),
+                    kind: ChangeKind::Added,
+                });
+            };
+            let old = self.diff.old.clone().nth(old_index)?;
+            let old = old.as_ref();
+
+            let change = |kind| {
+                Some(PartitionChange {
+                    old: Some(old_index),
+                    new: Some(new_index),
+                    kind,
+                })
+            };
+
+            match self.check {
+                Check::Match => {
+                    self.check = Check::Name;
+                }
+                Check::Name => {
+                    self.check = Check::Type;
+                    if old.name() != new.name() {
+                        return change(ChangeKind::Renamed);
+                    }
+                }
+                Check::Type => {
+                    self.check = Check::Offset;
+                    if old.type_ != new.type_ {
+                        return change(ChangeKind::Retyped);
+                    }
+                }
+                Check::Offset => {
+                    self.check = Check::Size;
+                    if old.offset != new.offset {
+                        return change(ChangeKind::Moved);
+                    }
+                }
+                Check::Size => {
+                    self.check = Check::Flags;
+                    if old.size != new.size {
+                        return change(ChangeKind::Resized);
+                    }
+                }
+                Check::Flags => {
+                    self.check = Check::Match;
+                    self.index += 1;
+                    if old.flags != new.flags {
+                        return change(ChangeKind::FlagsChanged);
+                    }
+                }
+                Check::Removed => unreachable!(),
+            }
+        }
+    }
+}
+
+/// Iterator over flash regions which belong to different partition
+pub struct RangeChangeIter<O, N> {
+    diff: PartitionDiff<O, N>,
+    offset: u64,
+}
+
+impl<O, N> Iterator for RangeChangeIter<O, N>
+where
+    O: Iterator + Clone,
+    O::Item: AsRef<PartitionEntry>,
+    N: Iterator + Clone,
+    N::Item: AsRef<PartitionEntry>,
+{
+    type Item = RangeChange;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.offset;
+            let end = self.diff.next_boundary(start)?;
+            self.offset = end;
+
+            let (old, new) = self.diff.owners(start);
+            let changed = match old {
+                Some(old) => {
+                    let counterpart = self.diff.new_index(old);
+                    counterpart.is_none() || counterpart != new
+                }
+                None => new.is_some(),
+            };
+            if !changed {
+                continue;
+            }
+
+            // Merge adjacent regions with the same owners
+            while self.diff.owners(self.offset) == (old, new) {
+                let Some(end) = self.diff.next_boundary(self.offset) else {
+                    break;
+                };
+                self.offset = end;
+            }
+
+            return Some(RangeChange {
+                offset: start as u32,
+                size: (self.offset - start) as usize,
+                old,
+                new,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn diff_layouts() {
+        let table = PartitionTable::default();
+        let old = table
+            .iter_csv(include_str!("../tests/partitions.csv"))
+            .map(Result::unwrap);
+        let new = table
+            .iter_csv(include_str!("../tests/partitions-ota.csv"))
+            .map(Result::unwrap);
+
+        let diff = PartitionDiff::new(old, new);
+        assert_eq!(diff.old_index(0), Some(0));
+        assert_eq!(diff.old_index(1), None);
+        assert_eq!(diff.old_index(3), Some(2));
+        assert_eq!(diff.new_index(4), Some(7));
+
+        let mut changes = diff.changes();
+        for (old, new, kind) in [
+            (Some(0), Some(0), ChangeKind::Resized),
+            (None, Some(1), ChangeKind::Added),
+            (Some(2), Some(3), ChangeKind::Resized),
+            (None, Some(4), ChangeKind::Added),
+            (None, Some(5), ChangeKind::Added),
+        ] {
+            assert_eq!(changes.next(), Some(PartitionChange { old, new, kind }));
+        }
+        assert_eq!(changes.next(), None);
+
+        let mut ranges = diff.range_changes();
+        for (offset, size, old, new) in [
+            (0xd000, 0x2000, Some(0), Some(1)),
+            (0x110000, 0x100000, Some(2), Some(4)),
+            (0x210000, 0x100000, Some(2), Some(5)),
+        ] {
+            assert_eq!(
+                ranges.next(),
+                Some(RangeChange {
+                    offset,
+                    size,
+                    old,
+                    new
+                })
+            );
+        }
+        assert_eq!(ranges.next(), None);
+    }
+
+    #[test]
+    fn diff_matching() {
+        let old = [
+            PartitionEntry::new(DataPartitionType::Nvs, 0x9000, 0x6000, "nvs", false).unwrap(),
+            PartitionEntry::new(AppPartitionType::Factory, 0x10000, 0x100000, "app", false)
+                .unwrap(),
+            PartitionEntry::new(
+                DataPartitionType::SpiFfs,
+                0x110000,
+                0x10000,
+                "storage",
+                false,
+            )
+            .unwrap(),
+            PartitionEntry::new(
+                DataPartitionType::CoreDump,
+                0x120000,
+                0x10000,
+                "coredump",
+                false,
+            )
+            .unwrap(),
+        ];
+        let new = [
+            PartitionEntry::new(DataPartitionType::Nvs, 0x9000, 0x6000, "nvs", true).unwrap(),
+            PartitionEntry::new(AppPartitionType::Factory, 0x10000, 0x100000, "main", false)
+                .unwrap(),
+            PartitionEntry::new(DataPartitionType::Fat, 0x120000, 0x10000, "storage", false)
+                .unwrap(),
+        ];
+
+        let diff = PartitionDiff::new(&old, &new);
+        assert_eq!(diff.old_index(1), Some(1));
+        assert_eq!(diff.new_index(1), Some(1));
+        assert_eq!(diff.new_index(3), None);
+
+        let changes = [
+            (Some(0), Some(0), ChangeKind::FlagsChanged),
+            (Some(1), Some(1), ChangeKind::Renamed),
+            (Some(2), Some(2), ChangeKind::Retyped),
+            (Some(2), Some(2), ChangeKind::Moved),
+            (Some(3), None, ChangeKind::Removed),
+        ];
+        assert!(diff
+            .changes()
+            .eq(changes.map(|(old, new, kind)| PartitionChange { old, new, kind })));
+
+        let ranges = [
+            (0x110000, 0x10000, Some(2), None),
+            (0x120000, 0x10000, Some(3), Some(2)),
+        ];
+        assert!(diff
+            .range_changes()
+            .eq(ranges.map(|(offset, size, old, new)| {
+                RangeChange {
+                    offset,
+                    size,
+                    old,
+                    new,
+                }
+            })));
+
+        // Region of removed partition becomes free
+        let diff = PartitionDiff::new(&old, &old[..3]);
+        assert!(diff.changes().eq([PartitionChange {
+            old: Some(3),
+            new: None,
+            kind: ChangeKind::Removed
+        }]));
+        assert!(diff.range_changes().eq([RangeChange {
+            offset: 0x120000,
+            size: 0x10000,
+            old: Some(3),
+            new: None
+        }]));
+        assert!(PartitionDiff::new(&old[..3], &old)
+            .range_changes()
+            .eq([RangeChange {
+                offset: 0x120000,
+                size: 0x10000,
+                old: None,
+                new: Some(3)
+            }]));
+
+        assert!(PartitionDiff::new(&new, &new).changes().next().is_none());
+        assert!(PartitionDiff::new(&new, &new)
+            .range_changes()
+            .next()
+            .is_none());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{utils, PartitionError, PartitionType};
+use core::ops::BitOr;
 
 #[cfg(feature = "heapless")]
 use heapless::String;
@@ -6,6 +7,97 @@ use heapless::String;
 /// Data buffer for partition entry
 pub type PartitionBuffer = [u8; PartitionEntry::SIZE];
 
+/// ESP Partition flags
+///
+/// Unknown bits are preserved as is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PartitionFlags(u32);
+
+impl PartitionFlags {
+    /// No flags
+    pub const NONE: Self = Self(0);
+
+    /// Partition is encrypted
+    pub const ENCRYPTED: Self = Self(1 << 0);
+
+    /// Partition is read-only
+    pub const READONLY: Self = Self(1 << 1);
+
+    /// Create flags from raw bits
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Get raw bits
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Get bits which is not known
+    pub const fn unknown_bits(&self) -> u32 {
+        self.0 & !(Self::ENCRYPTED.0 | Self::READONLY.0)
+    }
+
+    /// Partition encrypted flag
+    pub const fn encrypted(&self) -> bool {
+        self.0 & Self::ENCRYPTED.0 != 0
+    }
+
+    /// Partition read-only flag
+    pub const fn readonly(&self) -> bool {
+        self.0 & Self::READONLY.0 != 0
+    }
+
+    /// Set partition encrypted flag
+    pub fn set_encrypted(&mut self, encrypted: bool) {
+        self.set(Self::ENCRYPTED, encrypted);
+    }
+
+    /// Set partition read-only flag
+    pub fn set_readonly(&mut self, readonly: bool) {
+        self.set(Self::READONLY, readonly);
+    }
+
+    fn set(&mut self, mask: Self, value: bool) {
+        if value {
+            self.0 |= mask.0;
+        } else {
+            self.0 &= !mask.0;
+        }
+    }
+}
+
+impl BitOr for PartitionFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl From<u32> for PartitionFlags {
+    fn from(bits: u32) -> Self {
+        Self(bits)
+    }
+}
+
+impl From<PartitionFlags> for u32 {
+    fn from(flags: PartitionFlags) -> Self {
+        flags.0
+    }
+}
+
+/// Boolean value is treated as encrypted flag
+impl From<bool> for PartitionFlags {
+    fn from(encrypted: bool) -> Self {
+        if encrypted {
+            Self::ENCRYPTED
+        } else {
+            Self::NONE
+        }
+    }
+}
+
 /// ESP Partition info
 ///
 /// Binary representation:
@@ -38,8 +130,8 @@ pub struct PartitionEntry {
     #[cfg(not(feature = "heapless"))]
     name: [u8; Self::MAX_NAME_LEN],
 
-    /// Partition encrypted flag
-    pub encrypted: bool,
+    /// Partition flags
+    pub flags: PartitionFlags,
 }
 
 impl PartitionEntry {
@@ -53,12 +145,14 @@ impl PartitionEntry {
     pub const MAX_NAME_LEN: usize = 16;
 
     /// Create partition info
+    ///
+    /// The `flags` can be passed as [`PartitionFlags`], raw bits or boolean encrypted flag.
     pub fn new(
         type_: impl Into<PartitionType>,
         offset: u32,
         size: usize,
         name: impl AsRef<str>,
-        encrypted: bool,
+        flags: impl Into<PartitionFlags>,
     ) -> Result<Self, PartitionError> {
         let name = name.as_ref();
 
@@ -77,10 +171,16 @@ impl PartitionEntry {
             offset,
             size,
             name,
-            encrypted,
+            flags: flags.into(),
         })
     }
 
+    /// Partition is encrypted
+    #[deprecated(since = "0.2.0", note = "use `flags.encrypted()` instead")]
+    pub fn encrypted(&self) -> bool {
+        self.flags.encrypted()
+    }
+
     /// Set partition offset with alignment check
     pub fn set_offset(&mut self, offset: u32) -> Result<(), PartitionError> {
         self.type_.check_offset(offset)?;
@@ -121,6 +221,18 @@ impl PartitionEntry {
 
     /// Convert partition data from binary representation
     pub fn from_bytes(data: &PartitionBuffer) -> Result<Self, PartitionError> {
+        Self::decode(data, true)
+    }
+
+    /// Convert partition data from binary representation
+    ///
+    /// Unlike [`PartitionEntry::from_bytes`] unknown application and data subtypes
+    /// are represented as `Other` instead of error.
+    pub fn from_bytes_lenient(data: &PartitionBuffer) -> Result<Self, PartitionError> {
+        Self::decode(data, false)
+    }
+
+    fn decode(data: &PartitionBuffer, strict: bool) -> Result<Self, PartitionError> {
         let (magic, data) = data
             .split_first_chunk()
             .ok_or(PartitionError::NotEnoughData)?;
@@ -131,7 +243,11 @@ impl PartitionEntry {
         let (type_data, data) = data
             .split_first_chunk()
             .ok_or(PartitionError::NotEnoughData)?;
-        let type_ = type_data.try_into()?;
+        let type_ = if strict {
+            PartitionType::from_bytes(type_data)?
+        } else {
+            PartitionType::from_bytes_lenient(type_data)?
+        };
 
         let (offset_data, data) = data
             .split_first_chunk()
@@ -159,16 +275,14 @@ impl PartitionEntry {
         let (flags_data, _) = data
             .split_first_chunk()
             .ok_or(PartitionError::NotEnoughData)?;
-        let flags = u32::from_le_bytes(*flags_data);
-
-        let encrypted = flags & 0x01 != 0;
+        let flags = u32::from_le_bytes(*flags_data).into();
 
         Ok(Self {
             type_,
             offset,
             size,
             name,
-            encrypted,
+            flags,
         })
     }
 
@@ -211,7 +325,7 @@ impl PartitionEntry {
         let (flags_data, _) = data
             .split_first_chunk_mut()
             .ok_or(PartitionError::NotEnoughData)?;
-        *flags_data = (self.encrypted as u32).to_le_bytes();
+        *flags_data = self.flags.bits().to_le_bytes();
 
         Ok(())
     }
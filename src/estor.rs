@@ -1,8 +1,10 @@
 use crate::{
     PartitionBuffer, PartitionEntry, PartitionError, PartitionReaderState, PartitionTable,
-    PartitionWriterState,
 };
-use core::{mem::MaybeUninit, ops::Deref};
+use core::{
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+};
 use embedded_storage::{ReadStorage, Storage};
 
 /// Error type for embedded storage operations
@@ -72,6 +74,9 @@ impl PartitionTable {
 
     /// Write partitions into table
     ///
+    /// The partitions layout is validated against storage capacity before writing.
+    /// The whole table is written with unused space padded by `0xff` like `gen_esp32part.py` does.
+    ///
     /// If `md5` feature isn't enabled `write_md5` argument will be ignored.
     #[cfg(feature = "embedded-storage")]
     pub fn write_storage<S>(
@@ -83,35 +88,89 @@ impl PartitionTable {
     where
         S: Storage,
     {
-        let mut data = MaybeUninit::<PartitionBuffer>::uninit();
-        let mut state = PartitionWriterState::new(self.addr, self.size, write_md5);
+        let mut table_data = MaybeUninit::<[u8; PartitionTable::MAX_SIZE]>::uninit();
+        let table_data = unsafe { table_data.assume_init_mut() };
+        let table_data = &mut table_data[..self.size.min(PartitionTable::MAX_SIZE)];
 
-        for partition in partitions {
-            if state.is_done() {
-                return Err(PartitionError::TooManyData.into());
-            }
+        let size = self.write_bytes(table_data, partitions, write_md5)?;
 
-            state.write(unsafe { data.assume_init_mut() }, partition)?;
+        self.check_bytes(&table_data[..size], storage.capacity())?;
 
-            storage
-                .write(state.offset(), unsafe { data.assume_init_ref() })
-                .map_err(StorageOpError::StorageError)?;
-        }
+        storage
+            .write(self.addr, table_data)
+            .map_err(StorageOpError::StorageError)?;
 
-        #[cfg(feature = "md5")]
-        if write_md5 {
-            if state.is_done() {
-                return Err(PartitionError::TooManyData.into());
-            }
+        Ok(size)
+    }
+
+    /// Write partitions into table and verify result
+    ///
+    /// See [`PartitionTable::write_storage`] and [`PartitionTable::verify_storage`] for details.
+    #[cfg(feature = "embedded-storage")]
+    pub fn write_storage_verified<S, I>(
+        &self,
+        storage: &mut S,
+        partitions: I,
+        write_md5: bool,
+    ) -> Result<usize, StorageOpError<S>>
+    where
+        S: Storage,
+        I: IntoIterator,
+        I::IntoIter: Clone,
+        I::Item: AsRef<PartitionEntry>,
+    {
+        let partitions = partitions.into_iter();
+        let size = self.write_storage(storage, partitions.clone(), write_md5)?;
+        self.verify_storage(storage, partitions, write_md5)?;
+        Ok(size)
+    }
+
+    /// Verify that table contains specified partitions
+    ///
+    /// The partitions are read back and compared one by one.
+    /// The first differing or unreadable entry is reported as [`PartitionError::VerifyFailed`].
+    /// When `check_md5` is set the MD5 entry should be present and valid.
+    ///
+    /// If `md5` feature isn't enabled `check_md5` argument will be ignored.
+    #[cfg(feature = "embedded-storage")]
+    pub fn verify_storage<S>(
+        &self,
+        storage: &mut S,
+        partitions: impl IntoIterator<Item = impl AsRef<PartitionEntry>>,
+        check_md5: bool,
+    ) -> Result<(), StorageOpError<S>>
+    where
+        S: ReadStorage,
+    {
+        let mut iter = self.iter_storage(storage, check_md5);
+        iter.set_strict(false);
 
-            state.write_md5(unsafe { data.assume_init_mut() })?;
+        let mut partitions = partitions.into_iter();
+        let mut index = 0;
 
-            storage
-                .write(state.offset(), unsafe { data.assume_init_ref() })
-                .map_err(StorageOpError::StorageError)?;
+        loop {
+            let actual = match iter.next() {
+                Some(Ok(partition)) => Some(partition),
+                Some(Err(StorageOpError::PartitionError(_))) => {
+                    return Err(PartitionError::VerifyFailed(index).into());
+                }
+                Some(Err(error)) => return Err(error),
+                None => None,
+            };
+
+            match (actual, partitions.next()) {
+                (None, None) => break,
+                (Some(actual), Some(expected)) if actual == *expected.as_ref() => index += 1,
+                _ => return Err(PartitionError::VerifyFailed(index).into()),
+            }
         }
 
-        Ok((state.offset() - self.addr) as usize)
+        #[cfg(feature = "md5")]
+        if check_md5 && iter.check_md5() != Some(true) {
+            return Err(PartitionError::VerifyFailed(index).into());
+        }
+
+        Ok(())
     }
 }
 
@@ -154,6 +213,12 @@ impl<S> Deref for PartitionStorageIter<'_, S> {
     }
 }
 
+impl<S> DerefMut for PartitionStorageIter<'_, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.state
+    }
+}
+
 impl<S> Iterator for PartitionStorageIter<'_, S>
 where
     S: ReadStorage,
@@ -176,3 +241,107 @@ where
             .transpose()
     }
 }
+
+#[cfg(all(test, feature = "md5"))]
+mod test {
+    use crate::*;
+    use embedded_storage::{
+        nor_flash::{NorFlashErrorKind, RmwNorFlashStorage},
+        ReadStorage, Storage,
+    };
+
+    extern crate std;
+    use std::vec;
+
+    #[test]
+    fn verify_storage() {
+        let bin = include_bytes!("../tests/partitions-ota.bin");
+        let partitions = bin
+            .chunks_exact(PartitionEntry::SIZE)
+            .map_while(|data| PartitionEntry::try_from(data).ok());
+
+        let table = PartitionTable::default();
+        let mut buffer = [0; 0x1000];
+        let mut storage =
+            RmwNorFlashStorage::new(NorFlashSim::<_>::new(vec![0; 0x400000]), &mut buffer);
+        assert!(matches!(
+            table.write_storage_verified(&mut storage, partitions.clone(), true),
+            Ok(size) if size == 8 * PartitionEntry::SIZE
+        ));
+        let mut data = [0; 0x1000];
+        storage.read(0x8000, &mut data).unwrap();
+        assert_eq!(data[..0xc00], bin[..]);
+        assert_eq!(data[0xc00..], [0xff; 0x400]);
+
+        let addr = 0x8000 + 8 * PartitionEntry::SIZE as u32 + 0x10;
+        let mut byte = [0];
+        storage.read(addr, &mut byte).unwrap();
+        storage.write(addr, &[byte[0] ^ 1]).unwrap();
+        assert!(matches!(
+            table.verify_storage(&mut storage, partitions.clone(), true),
+            Err(StorageOpError::PartitionError(
+                PartitionError::VerifyFailed(8)
+            ))
+        ));
+        assert!(matches!(
+            table.verify_storage(&mut storage, partitions.clone(), false),
+            Ok(())
+        ));
+        assert!(matches!(
+            table.verify_storage(&mut storage, partitions.clone().skip(1), false),
+            Err(StorageOpError::PartitionError(
+                PartitionError::VerifyFailed(0)
+            ))
+        ));
+
+        let table = PartitionTable::new(0x400000, PartitionTable::MAX_SIZE);
+        assert!(matches!(
+            table.verify_storage(&mut storage, partitions, false),
+            Err(StorageOpError::StorageError(NorFlashErrorKind::OutOfBounds))
+        ));
+    }
+
+    #[test]
+    fn write_storage_one_shot() {
+        let bin = include_bytes!("../tests/partitions.bin");
+        let mut chunks = bin.chunks_exact(PartitionEntry::SIZE);
+        // Captures mutable reference so cannot be cloned
+        let partitions = core::iter::from_fn(|| PartitionEntry::try_from(chunks.next()?).ok());
+
+        let table = PartitionTable::default();
+        let mut buffer = [0; 0x1000];
+        let mut storage =
+            RmwNorFlashStorage::new(NorFlashSim::<_>::new(vec![0; 0x400000]), &mut buffer);
+        assert!(matches!(
+            table.write_storage(&mut storage, partitions, true),
+            Ok(size) if size == 5 * PartitionEntry::SIZE
+        ));
+        let mut data = [0xff; 0xc00];
+        storage.read(0x8000, &mut data).ok().unwrap();
+        assert_eq!(data[..], bin[..]);
+
+        let nvs =
+            PartitionEntry::new(DataPartitionType::Nvs, 0x9000, 0x6000, "nvs", false).unwrap();
+        let phy =
+            PartitionEntry::new(DataPartitionType::Phy, 0xe000, 0x1000, "phy", false).unwrap();
+        let mut partitions = [nvs, phy].into_iter();
+        let mut storage =
+            RmwNorFlashStorage::new(NorFlashSim::<_>::new(vec![0; 0x400000]), &mut buffer);
+        assert!(matches!(
+            table.write_storage(
+                &mut storage,
+                core::iter::from_fn(|| partitions.next()),
+                true
+            ),
+            Err(StorageOpError::PartitionError(
+                PartitionError::InvalidLayout(PartitionViolation {
+                    index: 1,
+                    kind: ViolationKind::Overlaps(0),
+                })
+            ))
+        ));
+        let mut data = vec![0xff; 0x400000];
+        storage.read(0, &mut data).ok().unwrap();
+        assert!(data.iter().all(|byte| *byte == 0));
+    }
+}
@@ -0,0 +1,667 @@
+use crate::{utils, PartitionError};
+
+#[cfg(feature = "embedded-storage")]
+use crate::{NorFlashOpError, PartitionEntry, PartitionType, StorageOpError};
+
+#[cfg(feature = "embedded-storage")]
+use embedded_storage::{nor_flash::ReadNorFlash, ReadStorage};
+
+/// SHA-256 digest
+pub type Sha256Data = [u8; 32];
+
+/// Application image header
+///
+/// The same as `esp_image_header_t` from ESP-IDF.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct AppImageHeader {
+    /// Number of segments
+    pub segment_count: u8,
+
+    /// SPI flash mode
+    pub spi_mode: u8,
+
+    /// SPI flash speed
+    pub spi_speed: u8,
+
+    /// SPI flash size
+    pub spi_size: u8,
+
+    /// Entry point address
+    pub entry_addr: u32,
+
+    /// WP pin when SPI pins set via efuse
+    pub wp_pin: u8,
+
+    /// Drive settings for the SPI flash pins
+    pub spi_pin_drv: [u8; 3],
+
+    /// Chip identifier
+    pub chip_id: u16,
+
+    /// Minimal chip revision (deprecated)
+    pub min_chip_rev: u8,
+
+    /// Minimal chip revision in format `major * 100 + minor`
+    pub min_chip_rev_full: u16,
+
+    /// Maximal chip revision in format `major * 100 + minor`
+    pub max_chip_rev_full: u16,
+
+    /// SHA-256 digest is appended after the image
+    pub hash_appended: bool,
+}
+
+impl AppImageHeader {
+    /// Magic byte of image header
+    pub const MAGIC: u8 = 0xe9;
+
+    /// Size of header in bytes
+    pub const SIZE: usize = 24;
+
+    /// Maximum number of segments
+    pub const MAX_SEGMENTS: usize = 16;
+
+    /// Convert header from binary representation
+    pub fn from_bytes(data: &[u8; Self::SIZE]) -> Result<Self, PartitionError> {
+        if data[0] != Self::MAGIC {
+            return Err(PartitionError::InvalidMagic);
+        }
+
+        let u16_at = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]);
+
+        Ok(Self {
+            segment_count: data[1],
+            spi_mode: data[2],
+            spi_speed: data[3] & 0x0f,
+            spi_size: data[3] >> 4,
+            entry_addr: u32::from_le_bytes([data[4], data[5], data[6], data[7]]),
+            wp_pin: data[8],
+            spi_pin_drv: [data[9], data[10], data[11]],
+            chip_id: u16_at(12),
+            min_chip_rev: data[14],
+            min_chip_rev_full: u16_at(15),
+            max_chip_rev_full: u16_at(17),
+            hash_appended: data[23] == 1,
+        })
+    }
+}
+
+/// Application image segment
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct AppImageSegment {
+    /// Address where segment is loaded
+    pub load_addr: u32,
+
+    /// Offset of segment data from image start
+    pub offset: u32,
+
+    /// Length of segment data
+    pub size: u32,
+}
+
+impl AppImageSegment {
+    /// Size of segment header in bytes
+    pub const HEADER_SIZE: usize = 8;
+}
+
+/// Application description
+///
+/// The same as `esp_app_desc_t` from ESP-IDF. It is located at the start of first segment data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AppDesc {
+    /// Secure version for anti-rollback
+    pub secure_version: u32,
+
+    /// Application version
+    pub version: [u8; 32],
+
+    /// Project name
+    pub project_name: [u8; 32],
+
+    /// Compile time
+    pub time: [u8; 16],
+
+    /// Compile date
+    pub date: [u8; 16],
+
+    /// ESP-IDF version
+    pub idf_ver: [u8; 32],
+
+    /// SHA-256 of application ELF file
+    pub app_elf_sha256: Sha256Data,
+
+    /// Minimal eFuse block revision in format `major * 100 + minor`
+    pub min_efuse_blk_rev_full: u16,
+
+    /// Maximal eFuse block revision in format `major * 100 + minor`
+    pub max_efuse_blk_rev_full: u16,
+
+    /// MMU page size in log base 2 format
+    pub mmu_page_size: u8,
+}
+
+impl AppDesc {
+    /// Magic word of application description
+    pub const MAGIC: u32 = 0xabcd5432;
+
+    /// Size of description in bytes
+    pub const SIZE: usize = 256;
+
+    /// Convert description from binary representation
+    pub fn from_bytes(data: &[u8; Self::SIZE]) -> Result<Self, PartitionError> {
+        let (magic, data) = data.split_first_chunk::<4>().unwrap();
+        if u32::from_le_bytes(*magic) != Self::MAGIC {
+            return Err(PartitionError::InvalidMagic);
+        }
+
+        let (secure_version, data) = data.split_first_chunk::<4>().unwrap();
+        let (_, data) = data.split_first_chunk::<8>().unwrap();
+        let (version, data) = data.split_first_chunk().unwrap();
+        let (project_name, data) = data.split_first_chunk().unwrap();
+        let (time, data) = data.split_first_chunk().unwrap();
+        let (date, data) = data.split_first_chunk().unwrap();
+        let (idf_ver, data) = data.split_first_chunk().unwrap();
+        let (app_elf_sha256, data) = data.split_first_chunk().unwrap();
+        let (min_efuse_blk_rev_full, data) = data.split_first_chunk().unwrap();
+        let (max_efuse_blk_rev_full, data) = data.split_first_chunk().unwrap();
+
+        Ok(Self {
+            secure_version: u32::from_le_bytes(*secure_version),
+            version: *version,
+            project_name: *project_name,
+            time: *time,
+            date: *date,
+            idf_ver: *idf_ver,
+            app_elf_sha256: *app_elf_sha256,
+            min_efuse_blk_rev_full: u16::from_le_bytes(*min_efuse_blk_rev_full),
+            max_efuse_blk_rev_full: u16::from_le_bytes(*max_efuse_blk_rev_full),
+            mmu_page_size: data[0],
+        })
+    }
+
+    /// Get application version
+    pub fn version(&self) -> &str {
+        utils::str_lossy(&self.version)
+    }
+
+    /// Get project name
+    pub fn project_name(&self) -> &str {
+        utils::str_lossy(&self.project_name)
+    }
+
+    /// Get compile time
+    pub fn time(&self) -> &str {
+        utils::str_lossy(&self.time)
+    }
+
+    /// Get compile date
+    pub fn date(&self) -> &str {
+        utils::str_lossy(&self.date)
+    }
+
+    /// Get ESP-IDF version
+    pub fn idf_ver(&self) -> &str {
+        utils::str_lossy(&self.idf_ver)
+    }
+}
+
+/// Application image info
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AppImage {
+    /// Image header
+    pub header: AppImageHeader,
+
+    /// Application description
+    ///
+    /// Images without description (like bootloader) has `None` here.
+    pub desc: Option<AppDesc>,
+
+    /// Image length including checksum and appended digest
+    ///
+    /// Available only when image was verified.
+    pub length: Option<usize>,
+
+    /// Appended SHA-256 digest
+    ///
+    /// Available only when image was verified.
+    pub sha256: Option<Sha256Data>,
+
+    segments: [AppImageSegment; AppImageHeader::MAX_SEGMENTS],
+    segment_count: usize,
+}
+
+impl AppImage {
+    /// Get read segments
+    ///
+    /// When image wasn't verified only the first segment is available.
+    pub fn segments(&self) -> &[AppImageSegment] {
+        &self.segments[..self.segment_count]
+    }
+
+    /// Read application image from partition
+    ///
+    /// See [`AppImageReaderState::new`] for `verify` argument.
+    #[cfg(feature = "embedded-storage")]
+    pub fn read_storage<S>(
+        storage: &mut S,
+        partition: &PartitionEntry,
+        verify: bool,
+    ) -> Result<Self, StorageOpError<S>>
+    where
+        S: ReadStorage,
+    {
+        let mut state = AppImageReaderState::new(verify);
+        let mut buffer = [0; AppImageReaderState::CHUNK_SIZE];
+
+        while !state.is_done() {
+            let offset = state.next_offset(partition)?;
+            storage
+                .read(offset, &mut buffer)
+                .map_err(StorageOpError::StorageError)?;
+            state.read(&buffer)?;
+        }
+
+        state.image().map_err(From::from)
+    }
+
+    /// Read application image from partition
+    ///
+    /// See [`AppImageReaderState::new`] for `verify` argument.
+    #[cfg(feature = "embedded-storage")]
+    pub fn read_nor_flash<S>(
+        storage: &mut S,
+        partition: &PartitionEntry,
+        verify: bool,
+    ) -> Result<Self, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        let mut state = AppImageReaderState::new(verify);
+        let mut buffer = [0; AppImageReaderState::CHUNK_SIZE];
+
+        // Assume that chunk size is multiple of S::READ_SIZE
+        while !state.is_done() {
+            let offset = state.next_offset(partition)?;
+            storage
+                .read(offset, &mut buffer)
+                .map_err(NorFlashOpError::StorageError)?;
+            state.read(&buffer)?;
+        }
+
+        state.image().map_err(From::from)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Stage {
+    Header,
+    SegmentHeader,
+    SegmentData(u32),
+    Padding,
+    Checksum,
+    Digest,
+    Done,
+}
+
+/// Application image reader state
+///
+/// The image data should be passed sequentially starting from the beginning.
+#[derive(Clone)]
+pub struct AppImageReaderState {
+    verify: bool,
+    stage: Stage,
+    offset: u32,
+
+    buffer: [u8; 32],
+    filled: usize,
+
+    desc: [u8; AppDesc::SIZE],
+    desc_filled: usize,
+
+    header: AppImageHeader,
+    segments: [AppImageSegment; AppImageHeader::MAX_SEGMENTS],
+    segment_count: usize,
+
+    checksum: u8,
+
+    #[cfg(feature = "sha2")]
+    sha256: sha2::Sha256,
+
+    stored_sha256: Option<Sha256Data>,
+}
+
+impl AppImageReaderState {
+    /// Recommended size of data chunks
+    pub const CHUNK_SIZE: usize = 256;
+
+    /// Initial value of checksum
+    const CHECKSUM_SEED: u8 = 0xef;
+
+    /// Instantiate reader state
+    ///
+    /// When `verify` is `true` the whole image will be read to check the checksum and
+    /// the appended SHA-256 digest (only when `sha2` feature is enabled).
+    /// Otherwise the reading stops after application description.
+    pub fn new(verify: bool) -> Self {
+        Self {
+            verify,
+            stage: Stage::Header,
+            offset: 0,
+
+            buffer: [0; 32],
+            filled: 0,
+
+            desc: [0; AppDesc::SIZE],
+            desc_filled: 0,
+
+            header: AppImageHeader::default(),
+            segments: [AppImageSegment::default(); AppImageHeader::MAX_SEGMENTS],
+            segment_count: 0,
+
+            checksum: Self::CHECKSUM_SEED,
+
+            #[cfg(feature = "sha2")]
+            sha256: Default::default(),
+
+            stored_sha256: None,
+        }
+    }
+
+    /// Get number of processed bytes
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Reader reached end of image
+    pub fn is_done(&self) -> bool {
+        matches!(self.stage, Stage::Done)
+    }
+
+    /// Get offset of next chunk in partition
+    #[cfg(feature = "embedded-storage")]
+    fn next_offset(&self, partition: &PartitionEntry) -> Result<u32, PartitionError> {
+        if !matches!(partition.type_, PartitionType::App(_)) {
+            return Err(PartitionError::InvalidPartition);
+        }
+        if self.offset as usize + Self::CHUNK_SIZE > partition.size {
+            return Err(PartitionError::NotEnoughData);
+        }
+        Ok(partition.offset + self.offset)
+    }
+
+    /// Read image data
+    ///
+    /// The data after the end of image is ignored.
+    pub fn read(&mut self, mut data: &[u8]) -> Result<(), PartitionError> {
+        while !data.is_empty() && !self.is_done() {
+            let need = match self.stage {
+                Stage::Header => AppImageHeader::SIZE - self.filled,
+                Stage::SegmentHeader => AppImageSegment::HEADER_SIZE - self.filled,
+                Stage::SegmentData(left) => left as usize,
+                Stage::Padding => 15 - (self.offset as usize & 15),
+                Stage::Checksum => 1,
+                Stage::Digest => 32 - self.filled,
+                Stage::Done => 0,
+            };
+
+            let (head, rest) = data.split_at(need.min(data.len()));
+            data = rest;
+
+            #[cfg(feature = "sha2")]
+            if self.verify && !matches!(self.stage, Stage::Digest) {
+                sha2::Digest::update(&mut self.sha256, head);
+            }
+
+            self.process(head)?;
+            self.offset += head.len() as u32;
+        }
+
+        Ok(())
+    }
+
+    fn accumulate(&mut self, data: &[u8]) -> bool {
+        self.buffer[self.filled..][..data.len()].copy_from_slice(data);
+        self.filled += data.len();
+        let full = match self.stage {
+            Stage::Header => AppImageHeader::SIZE,
+            Stage::SegmentHeader => AppImageSegment::HEADER_SIZE,
+            _ => 32,
+        };
+        if self.filled == full {
+            self.filled = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn process(&mut self, data: &[u8]) -> Result<(), PartitionError> {
+        match self.stage {
+            Stage::Header => {
+                if self.accumulate(data) {
+                    self.header = AppImageHeader::from_bytes(self.buffer.first_chunk().unwrap())?;
+                    if self.header.segment_count as usize > AppImageHeader::MAX_SEGMENTS {
+                        return Err(PartitionError::TooManyData);
+                    }
+                    self.next_segment();
+                }
+            }
+            Stage::SegmentHeader => {
+                if self.accumulate(data) {
+                    let (load_addr, size) = self.buffer.split_at(4);
+                    let size = u32::from_le_bytes(size[..4].try_into().unwrap());
+                    self.segments[self.segment_count] = AppImageSegment {
+                        load_addr: u32::from_le_bytes(load_addr.try_into().unwrap()),
+                        offset: self.offset + data.len() as u32,
+                        size,
+                    };
+                    self.segment_count += 1;
+                    self.stage = Stage::SegmentData(size);
+                    if size == 0 {
+                        self.next_segment();
+                    }
+                }
+            }
+            Stage::SegmentData(left) => {
+                self.checksum = data.iter().fold(self.checksum, |sum, byte| sum ^ byte);
+
+                if self.segment_count == 1 && self.desc_filled < AppDesc::SIZE {
+                    let len = data.len().min(AppDesc::SIZE - self.desc_filled);
+                    self.desc[self.desc_filled..][..len].copy_from_slice(&data[..len]);
+                    self.desc_filled += len;
+                }
+
+                let left = left - data.len() as u32;
+                self.stage = Stage::SegmentData(left);
+                if !self.verify && self.desc_filled == AppDesc::SIZE {
+                    self.stage = Stage::Done;
+                } else if left == 0 {
+                    self.next_segment();
+                }
+            }
+            Stage::Padding => {
+                if (self.offset as usize + data.len()) & 15 == 15 {
+                    self.stage = Stage::Checksum;
+                }
+            }
+            Stage::Checksum => {
+                if data[0] != self.checksum {
+                    return Err(PartitionError::InvalidChecksum);
+                }
+                self.stage = if self.header.hash_appended {
+                    Stage::Digest
+                } else {
+                    Stage::Done
+                };
+            }
+            Stage::Digest => {
+                if self.accumulate(data) {
+                    #[cfg(feature = "sha2")]
+                    if sha2::Digest::finalize(self.sha256.clone())[..] != self.buffer {
+                        return Err(PartitionError::InvalidSha256);
+                    }
+                    self.stored_sha256 = Some(self.buffer);
+                    self.stage = Stage::Done;
+                }
+            }
+            Stage::Done => {}
+        }
+
+        Ok(())
+    }
+
+    fn next_segment(&mut self) {
+        self.stage = if !self.verify && self.segment_count > 0 {
+            // Image is too short to hold application description
+            Stage::Done
+        } else if self.segment_count < self.header.segment_count as usize {
+            Stage::SegmentHeader
+        } else if self.offset & 15 == 15 {
+            Stage::Checksum
+        } else {
+            Stage::Padding
+        };
+    }
+
+    /// Get read image info
+    pub fn image(&self) -> Result<AppImage, PartitionError> {
+        if !self.is_done() {
+            return Err(PartitionError::NotEnoughData);
+        }
+
+        Ok(AppImage {
+            header: self.header,
+            desc: (self.desc_filled == AppDesc::SIZE)
+                .then(|| AppDesc::from_bytes(&self.desc).ok())
+                .flatten(),
+            length: self.verify.then_some(self.offset as usize),
+            sha256: self.stored_sha256,
+            segments: self.segments,
+            segment_count: self.segment_count,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    // Generated by `tests/gen_app.py`
+    const IMAGE: &[u8] = include_bytes!("../tests/app.bin");
+
+    fn read_image(data: &[u8], verify: bool) -> Result<AppImage, PartitionError> {
+        let mut state = AppImageReaderState::new(verify);
+        for chunk in data.chunks(AppImageReaderState::CHUNK_SIZE) {
+            state.read(chunk)?;
+        }
+        state.image()
+    }
+
+    #[test]
+    fn read_header() {
+        let image = read_image(IMAGE, false).unwrap();
+
+        assert_eq!(image.header.segment_count, 2);
+        assert_eq!(image.header.spi_mode, 2);
+        assert_eq!(image.header.spi_size, 2);
+        assert_eq!(image.header.entry_addr, 0x400810a0);
+        assert_eq!(image.header.max_chip_rev_full, 399);
+        assert!(image.header.hash_appended);
+        assert_eq!(image.length, None);
+
+        assert_eq!(
+            image.segments(),
+            &[AppImageSegment {
+                load_addr: 0x3f400020,
+                offset: 32,
+                size: 268,
+            }]
+        );
+
+        let desc = image.desc.unwrap();
+        assert_eq!(desc.secure_version, 1);
+        assert_eq!(desc.version(), "1.2.3");
+        assert_eq!(desc.project_name(), "hello_world");
+        assert_eq!(desc.time(), "12:34:56");
+        assert_eq!(desc.date(), "Oct 16 2026");
+        assert_eq!(desc.idf_ver(), "v5.3.1");
+        assert_eq!(desc.app_elf_sha256[31], 31);
+        assert_eq!(desc.max_efuse_blk_rev_full, 99);
+        assert_eq!(desc.mmu_page_size, 16);
+    }
+
+    #[test]
+    fn verify_image() {
+        let image = read_image(IMAGE, true).unwrap();
+
+        assert_eq!(image.segments().len(), 2);
+        assert_eq!(image.segments()[1].load_addr, 0x40080000);
+        assert_eq!(image.segments()[1].offset, 308);
+        assert_eq!(image.segments()[1].size, 64);
+        assert_eq!(image.length, Some(IMAGE.len()));
+        assert_eq!(image.sha256.as_ref(), IMAGE.last_chunk());
+
+        // Reading stops at the end of image
+        let mut data = [0xff; 1024];
+        data[..IMAGE.len()].copy_from_slice(IMAGE);
+        assert_eq!(read_image(&data, true), Ok(image));
+
+        let mut data = [0; IMAGE.len()];
+        data.copy_from_slice(IMAGE);
+        data[200] ^= 1;
+        assert_eq!(
+            read_image(&data, true),
+            Err(PartitionError::InvalidChecksum)
+        );
+
+        #[cfg(feature = "sha2")]
+        {
+            data[200] ^= 1;
+            data[8] ^= 1;
+            assert_eq!(read_image(&data, true), Err(PartitionError::InvalidSha256));
+        }
+
+        assert_eq!(
+            read_image(&IMAGE[..200], true),
+            Err(PartitionError::NotEnoughData)
+        );
+        assert_eq!(
+            read_image(&IMAGE[1..], false),
+            Err(PartitionError::InvalidMagic)
+        );
+    }
+
+    #[cfg(feature = "embedded-storage")]
+    #[test]
+    fn read_storage() {
+        use embedded_storage::nor_flash::RmwNorFlashStorage;
+
+        let mut data = [0xff; 0x20000];
+        data[0x10000..][..IMAGE.len()].copy_from_slice(IMAGE);
+        let mut buffer = [0; 0x1000];
+        let mut storage = RmwNorFlashStorage::new(NorFlashSim::<_>::new(data), &mut buffer);
+
+        let factory = PartitionEntry::new(
+            AppPartitionType::Factory,
+            0x10000,
+            0x10000,
+            "factory",
+            false,
+        )
+        .unwrap();
+        let image = AppImage::read_storage(&mut storage, &factory, true)
+            .ok()
+            .unwrap();
+        assert_eq!(image.desc.unwrap().version(), "1.2.3");
+        assert_eq!(image.length, Some(IMAGE.len()));
+
+        let nvs =
+            PartitionEntry::new(DataPartitionType::Nvs, 0x9000, 0x6000, "nvs", false).unwrap();
+        assert!(matches!(
+            AppImage::read_storage(&mut storage, &nvs, false),
+            Err(StorageOpError::PartitionError(
+                PartitionError::InvalidPartition
+            ))
+        ));
+    }
+}
@@ -3,11 +3,27 @@
 #![deny(bad_style, missing_docs)]
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod csv;
+mod diff;
 mod entry;
+mod image;
+mod list;
+mod nvs;
+mod nvsenc;
+mod nvsgen;
+mod ota;
 mod result;
 mod table;
 mod types;
 mod utils;
+mod validate;
+mod view;
+
+#[cfg(feature = "serde")]
+mod ser;
 
 #[cfg(feature = "embedded-storage")]
 mod estor;
@@ -15,13 +31,62 @@ mod estor;
 #[cfg(feature = "embedded-storage")]
 mod norfl;
 
-pub use entry::{Md5Data, PartitionBuffer, PartitionEntry, PartitionMd5};
+#[cfg(feature = "embedded-storage")]
+mod pstor;
+
+#[cfg(feature = "embedded-storage-async")]
+mod anorfl;
+
+#[cfg(all(feature = "embedded-storage", any(test, feature = "testing")))]
+mod sim;
+
+pub use csv::{CsvError, CsvErrorKind, PartitionCsvIter};
+pub use diff::{
+    ChangeKind, PartitionChange, PartitionChangeIter, PartitionDiff, RangeChange, RangeChangeIter,
+};
+pub use entry::{Md5Data, PartitionBuffer, PartitionEntry, PartitionFlags, PartitionMd5};
+pub use image::{
+    AppDesc, AppImage, AppImageHeader, AppImageReaderState, AppImageSegment, Sha256Data,
+};
+pub use list::{PartitionList, PartitionVec};
+pub use nvs::{
+    NvsEntry, NvsEntryState, NvsEntryStates, NvsItem, NvsPageHeader, NvsPageState, NvsPartition,
+    NvsType, NvsValue,
+};
+pub use nvsenc::NvsKeys;
+pub use nvsgen::{NvsCsvEntry, NvsCsvIter, NvsCsvKind, NvsEncoding, NvsImageWriter};
+pub use ota::{OtaData, OtaImageState, OtaSelectEntry};
 pub use result::PartitionError;
 pub use table::{PartitionReaderState, PartitionTable, PartitionWriterState};
-pub use types::{AppPartitionType, DataPartitionType, PartitionType};
+pub use types::{
+    AppPartitionType, BootloaderPartitionType, DataPartitionType, PartitionType, TablePartitionType,
+};
+pub use validate::{PartitionViolation, PartitionViolationIter, ViolationKind};
+pub use view::{PartitionEntryView, PartitionTableView, PartitionViewIter};
+
+#[cfg(feature = "heapless")]
+pub use list::HeaplessPartitionList;
+
+#[cfg(feature = "alloc")]
+pub use list::AllocPartitionList;
 
 #[cfg(feature = "embedded-storage")]
 pub use estor::{PartitionStorageIter, StorageOpError};
 
 #[cfg(feature = "embedded-storage")]
 pub use norfl::{NorFlashOpError, PartitionNorFlashIter};
+
+#[cfg(feature = "embedded-storage")]
+pub use pstor::PartitionStorage;
+
+#[cfg(feature = "embedded-storage-async")]
+pub use anorfl::PartitionNorFlashAsyncIter;
+
+#[cfg(all(feature = "embedded-storage", any(test, feature = "testing")))]
+pub use sim::NorFlashSim;
+
+#[cfg(feature = "embedded-storage")]
+pub use nvs::NvsNorFlashIter;
+
+#[cfg(feature = "aes")]
+pub use nvsenc::NvsCipher;
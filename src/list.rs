@@ -0,0 +1,217 @@
+use crate::{AppPartitionType, PartitionEntry, PartitionError, PartitionTable, PartitionType};
+use core::ops::{Deref, DerefMut};
+
+/// Container which holds partition list entries
+pub trait PartitionVec: Default + Deref<Target = [PartitionEntry]> + DerefMut {
+    /// Append partition or give it back when container is full
+    fn push(&mut self, partition: PartitionEntry) -> Result<(), PartitionEntry>;
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> PartitionVec for heapless::Vec<PartitionEntry, N> {
+    fn push(&mut self, partition: PartitionEntry) -> Result<(), PartitionEntry> {
+        heapless::Vec::push(self, partition)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PartitionVec for alloc::vec::Vec<PartitionEntry> {
+    fn push(&mut self, partition: PartitionEntry) -> Result<(), PartitionEntry> {
+        alloc::vec::Vec::push(self, partition);
+        Ok(())
+    }
+}
+
+/// Partition list backed by `heapless::Vec`
+#[cfg(feature = "heapless")]
+pub type HeaplessPartitionList =
+    PartitionList<heapless::Vec<PartitionEntry, { PartitionTable::MAX_DATA_ENTRIES }>>;
+
+/// Partition list backed by `alloc::vec::Vec`
+#[cfg(feature = "alloc")]
+pub type AllocPartitionList = PartitionList<alloc::vec::Vec<PartitionEntry>>;
+
+/// Owned list of partitions
+///
+/// The number of partitions is limited by [`PartitionTable::MAX_DATA_ENTRIES`].
+/// The list can be collected from any partitions reader.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PartitionList<V> {
+    partitions: V,
+}
+
+impl<V: PartitionVec> PartitionList<V> {
+    /// Create empty list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append partition to the end of list
+    pub fn push(&mut self, partition: PartitionEntry) -> Result<(), PartitionError> {
+        if self.partitions.len() >= PartitionTable::MAX_DATA_ENTRIES {
+            return Err(PartitionError::TooManyData);
+        }
+        self.partitions
+            .push(partition)
+            .map_err(|_| PartitionError::TooManyData)
+    }
+
+    /// Get underlying container
+    pub fn into_inner(self) -> V {
+        self.partitions
+    }
+
+    /// Find partition by name
+    pub fn find(&self, name: &str) -> Option<&PartitionEntry> {
+        self.iter().find(|partition| partition.name() == name)
+    }
+
+    /// Find first partition with specified type and subtype
+    pub fn find_type(&self, type_: impl Into<PartitionType>) -> Option<&PartitionEntry> {
+        let type_ = type_.into();
+        self.iter().find(|partition| partition.type_ == type_)
+    }
+
+    /// Iterate over partitions with specified type and subtype
+    pub fn filter_type(
+        &self,
+        type_: impl Into<PartitionType>,
+    ) -> impl Iterator<Item = &PartitionEntry> {
+        let type_ = type_.into();
+        self.iter()
+            .filter(move |partition| partition.type_ == type_)
+    }
+
+    /// Iterate over OTA application slots in order of numbers
+    pub fn ota_slots(&self) -> impl Iterator<Item = &PartitionEntry> {
+        (0..=0x0f).filter_map(|number| self.find_type(AppPartitionType::Ota(number)))
+    }
+
+    /// Sort partitions by offset
+    pub fn sort_by_offset(&mut self) {
+        self.sort_unstable_by_key(|partition| partition.offset);
+    }
+
+    /// Encode complete partition table into buffer
+    ///
+    /// The unused space is filled by `0xff` like `gen_esp32part.py` does.
+    /// Returns the size of written partitions without MD5 entry.
+    ///
+    /// If `md5` feature isn't enabled `write_md5` argument will be ignored.
+    pub fn to_bytes(&self, data: &mut [u8], write_md5: bool) -> Result<usize, PartitionError> {
+        PartitionTable::new(0, data.len()).write_bytes(data, self.iter(), write_md5)
+    }
+}
+
+impl<V: PartitionVec> Deref for PartitionList<V> {
+    type Target = [PartitionEntry];
+
+    fn deref(&self) -> &Self::Target {
+        &self.partitions
+    }
+}
+
+impl<V: PartitionVec> DerefMut for PartitionList<V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.partitions
+    }
+}
+
+impl<V: PartitionVec> FromIterator<PartitionEntry> for PartitionList<V> {
+    /// Collect partitions into list
+    ///
+    /// # Panics
+    ///
+    /// Panics when number of partitions exceeds capacity.
+    fn from_iter<I: IntoIterator<Item = PartitionEntry>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<V: PartitionVec> Extend<PartitionEntry> for PartitionList<V> {
+    /// Append partitions to list
+    ///
+    /// # Panics
+    ///
+    /// Panics when number of partitions exceeds capacity.
+    fn extend<I: IntoIterator<Item = PartitionEntry>>(&mut self, iter: I) {
+        for partition in iter {
+            self.push(partition).expect("Too many partitions");
+        }
+    }
+}
+
+impl<'a, V: PartitionVec> IntoIterator for &'a PartitionList<V> {
+    type Item = &'a PartitionEntry;
+    type IntoIter = core::slice::Iter<'a, PartitionEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(all(test, feature = "md5", any(feature = "heapless", feature = "alloc")))]
+mod test {
+    use crate::*;
+
+    const CSV: &str = include_str!("../tests/partitions-ota.csv");
+    const BIN: &[u8] = include_bytes!("../tests/partitions-ota.bin");
+
+    fn check_list<V: PartitionVec>() {
+        let table = PartitionTable::default();
+        let mut list: PartitionList<V> = table.read_csv(CSV).unwrap();
+        assert_eq!(list.len(), 8);
+
+        assert_eq!(list.find("otadata").unwrap().offset, 0xd000);
+        assert!(list.find("unknown").is_none());
+        assert_eq!(
+            list.find_type(DataPartitionType::Nvs).unwrap().name(),
+            "nvs"
+        );
+        assert_eq!(list.filter_type(DataPartitionType::Nvs).count(), 2);
+        assert!(list
+            .ota_slots()
+            .map(|partition| partition.offset)
+            .eq([0x110000, 0x210000]));
+
+        let mut data = [0; 0xc00];
+        assert_eq!(list.to_bytes(&mut data, true), Ok(8 * PartitionEntry::SIZE));
+        assert_eq!(data[..], BIN[..]);
+
+        list.swap(0, 7);
+        list.sort_by_offset();
+        let mut copy = [0; 0xc00];
+        list.to_bytes(&mut copy, true).unwrap();
+        assert_eq!(copy, data);
+
+        let mut data = [0; 8 * PartitionEntry::SIZE];
+        assert_eq!(
+            list.to_bytes(&mut data, true),
+            Err(PartitionError::TooManyData)
+        );
+
+        while list.len() < PartitionTable::MAX_DATA_ENTRIES {
+            list.push(list[0].clone()).unwrap();
+        }
+        assert_eq!(list.push(list[0].clone()), Err(PartitionError::TooManyData));
+        let mut data = [0; 0xc00];
+        assert_eq!(
+            list.to_bytes(&mut data, false),
+            Ok(PartitionTable::MAX_DATA_SIZE - PartitionEntry::SIZE)
+        );
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn heapless_list() {
+        check_list::<heapless::Vec<PartitionEntry, { PartitionTable::MAX_DATA_ENTRIES }>>();
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn alloc_list() {
+        check_list::<alloc::vec::Vec<PartitionEntry>>();
+    }
+}
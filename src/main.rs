@@ -0,0 +1,407 @@
+//! Command-line tool for converting and inspecting ESP partition tables
+
+use clap::{Parser, Subcommand};
+use esp_partition_table::{
+    CsvError, NvsCsvIter, NvsCsvKind, NvsKeys, NvsPartition, PartitionEntry, PartitionError,
+    PartitionFlags, PartitionReaderState, PartitionTable, PartitionType,
+};
+use std::{
+    fmt, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+/// Convert and inspect ESP partition tables
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Partition table address
+    #[arg(long, default_value = "0x8000", value_parser = parse_addr)]
+    offset: u32,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert CSV to binary or binary to CSV
+    ///
+    /// The format of input is detected automatically.
+    Convert {
+        /// Input file (use `-` for stdin)
+        input: PathBuf,
+
+        /// Output file (use `-` for stdout)
+        output: PathBuf,
+
+        /// Omit offsets which can be computed automatically (CSV output only)
+        #[arg(long)]
+        minimal: bool,
+
+        /// Do not append MD5 checksum entry (binary output only)
+        #[arg(long)]
+        no_md5: bool,
+    },
+
+    /// Pretty-print partition table
+    Show {
+        /// Input file (use `-` for stdin)
+        input: PathBuf,
+    },
+
+    /// Verify MD5 checksum of binary partition table
+    Verify {
+        /// Input file (use `-` for stdin)
+        input: PathBuf,
+    },
+
+    /// Generate NVS partition image from CSV
+    ///
+    /// The image size is taken from partition in table.
+    Nvs {
+        /// Partition table file (CSV or binary)
+        #[arg(long)]
+        table: PathBuf,
+
+        /// Name of NVS partition
+        #[arg(long, default_value = "nvs")]
+        partition: String,
+
+        /// Encrypt image using keys from nvs_keys partition image
+        #[arg(long)]
+        keys: Option<PathBuf>,
+
+        /// Input CSV file (use `-` for stdin)
+        input: PathBuf,
+
+        /// Output file (use `-` for stdout)
+        output: PathBuf,
+    },
+}
+
+fn parse_addr(value: &str) -> Result<u32, String> {
+    let result = if let Some(hex) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        u32::from_str_radix(hex, 16)
+    } else {
+        value.parse()
+    };
+    result.map_err(|error| error.to_string())
+}
+
+enum Error {
+    Io(io::Error),
+    Csv(CsvError),
+    Partition(PartitionError),
+    Format(fmt::Error),
+    MissingPartition(String),
+    MissingKeys,
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<CsvError> for Error {
+    fn from(error: CsvError) -> Self {
+        Self::Csv(error)
+    }
+}
+
+impl From<PartitionError> for Error {
+    fn from(error: PartitionError) -> Self {
+        Self::Partition(error)
+    }
+}
+
+impl From<fmt::Error> for Error {
+    fn from(error: fmt::Error) -> Self {
+        Self::Format(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => error.fmt(f),
+            Self::Csv(error) => {
+                "CSV error at ".fmt(f)?;
+                error.fmt(f)
+            }
+            Self::Partition(error) => error.fmt(f),
+            Self::Format(error) => error.fmt(f),
+            Self::MissingPartition(name) => {
+                "Partition not found: ".fmt(f)?;
+                name.fmt(f)
+            }
+            Self::MissingKeys => "Keys are not initialized".fmt(f),
+        }
+    }
+}
+
+/// Loaded partition table
+struct Table {
+    partitions: Vec<PartitionEntry>,
+    /// MD5 check result for binary input
+    md5: Option<bool>,
+}
+
+impl Table {
+    /// Reject table which has MD5 checksum that doesn't match
+    fn valid_md5(self) -> Result<Self, Error> {
+        if self.md5 == Some(false) {
+            return Err(PartitionError::InvalidMd5.into());
+        }
+        Ok(self)
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), Error> {
+    let table = PartitionTable::new(args.offset, PartitionTable::MAX_SIZE);
+
+    match args.command {
+        Command::Convert {
+            input,
+            output,
+            minimal,
+            no_md5,
+        } => {
+            let data = read_input(&input)?;
+            let data = if is_binary(&data) {
+                let loaded = read_binary(&data, false)?.valid_md5()?;
+                let mut text = String::new();
+                table.write_csv(&mut text, &loaded.partitions, minimal)?;
+                text.into_bytes()
+            } else {
+                let loaded = read_text(&table, &data)?;
+                write_binary(&loaded.partitions, !no_md5)?
+            };
+            write_output(&output, &data)?;
+        }
+        Command::Show { input } => {
+            let data = read_input(&input)?;
+            let loaded = if is_binary(&data) {
+                read_binary(&data, false)?
+            } else {
+                read_text(&table, &data)?
+            };
+            show(&loaded)?;
+        }
+        Command::Verify { input } => {
+            let data = read_input(&input)?;
+            let loaded = read_binary(&data, true)?;
+            println!("{} partitions, MD5 is valid", loaded.partitions.len());
+        }
+        Command::Nvs {
+            table: table_path,
+            partition,
+            keys,
+            input,
+            output,
+        } => {
+            let data = read_input(&table_path)?;
+            let loaded = if is_binary(&data) {
+                read_binary(&data, false)?.valid_md5()?
+            } else {
+                read_text(&table, &data)?
+            };
+            let entry = loaded
+                .partitions
+                .iter()
+                .find(|entry| entry.name() == partition)
+                .ok_or(Error::MissingPartition(partition))?;
+            let mut nvs = NvsPartition::try_from(entry)?;
+            if let Some(path) = keys {
+                let data = read_input(&path)?;
+                let data = data.first_chunk().ok_or(PartitionError::NotEnoughData)?;
+                nvs = nvs.with_keys(NvsKeys::from_bytes(data)?.ok_or(Error::MissingKeys)?);
+            }
+
+            let data = read_input(&input)?;
+            let text = std::str::from_utf8(&data).map_err(|_| PartitionError::InvalidString)?;
+            let data = write_nvs(&nvs, text)?;
+            write_output(&output, &data)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn read_input(path: &Path) -> Result<Vec<u8>, Error> {
+    if path == Path::new("-") {
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        Ok(fs::read(path)?)
+    }
+}
+
+fn write_output(path: &Path, data: &[u8]) -> Result<(), Error> {
+    if path == Path::new("-") {
+        io::stdout().write_all(data)?;
+    } else {
+        fs::write(path, data)?;
+    }
+    Ok(())
+}
+
+/// Anything which isn't a text is considered binary
+///
+/// Tables with damaged first entry and erased flash dumps are reported by binary reader.
+fn is_binary(data: &[u8]) -> bool {
+    data.starts_with(&PartitionEntry::MAGIC) || std::str::from_utf8(data).is_err()
+}
+
+fn read_text(table: &PartitionTable, data: &[u8]) -> Result<Table, Error> {
+    let text = std::str::from_utf8(data).map_err(|_| PartitionError::InvalidString)?;
+    let partitions = table.read_csv(text)?;
+    Ok(Table {
+        partitions,
+        md5: None,
+    })
+}
+
+fn read_binary(data: &[u8], mandatory_md5: bool) -> Result<Table, Error> {
+    let mut reader = PartitionReaderState::new(0, data.len(), true);
+    reader.set_strict(false);
+    let mut partitions = Vec::new();
+
+    for chunk in data.chunks_exact(PartitionEntry::SIZE) {
+        match reader.read(chunk.try_into().unwrap()) {
+            Ok(partition) => partitions.push(partition),
+            Err(PartitionError::NotEnoughData) => break,
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    let md5 = reader.check_md5();
+
+    if mandatory_md5 && md5 != Some(true) {
+        return Err(PartitionError::InvalidMd5.into());
+    }
+
+    // Erased flash contains no table at all
+    if partitions.is_empty() {
+        return Err(PartitionError::NotEnoughData.into());
+    }
+
+    Ok(Table { partitions, md5 })
+}
+
+fn write_binary(partitions: &[PartitionEntry], write_md5: bool) -> Result<Vec<u8>, Error> {
+    let mut data = vec![0xff; PartitionTable::MAX_DATA_SIZE];
+    PartitionTable::new(0, data.len()).write_bytes(&mut data, partitions, write_md5)?;
+
+    Ok(data)
+}
+
+fn write_nvs(nvs: &NvsPartition, text: &str) -> Result<Vec<u8>, Error> {
+    // Files are loaded before generation to report I/O errors
+    let mut files = Vec::new();
+    for entry in NvsCsvIter::new(text) {
+        let entry = entry?;
+        if let NvsCsvKind::File(_) = entry.kind {
+            files.push((entry.value, fs::read(entry.value)?));
+        }
+    }
+
+    let mut data = vec![0xff; nvs.size];
+    let mut writer = nvs.image_writer(&mut data)?;
+    let mut buffer = vec![0; writer.max_blob_size()];
+    writer.add_csv(text, &mut buffer, |path| {
+        files
+            .iter()
+            .find(|(file, _)| *file == path)
+            .map(|(_, data)| data.as_slice())
+    })?;
+    writer.finish();
+
+    Ok(data)
+}
+
+fn type_names(type_: &PartitionType) -> (String, String) {
+    match type_ {
+        PartitionType::App(subtype) => ("app".into(), subtype.to_string()),
+        PartitionType::Data(subtype) => ("data".into(), subtype.to_string()),
+        PartitionType::Bootloader(subtype) => ("bootloader".into(), subtype.to_string()),
+        PartitionType::PartitionTable(subtype) => ("partition_table".into(), subtype.to_string()),
+        PartitionType::User(type_, subtype) => (format!("{type_:#04x}"), format!("{subtype:#04x}")),
+        PartitionType::Any => ("any".into(), String::new()),
+    }
+}
+
+fn format_size(size: usize) -> String {
+    if size % (1 << 20) == 0 {
+        format!("{}M", size >> 20)
+    } else if size % (1 << 10) == 0 {
+        format!("{}K", size >> 10)
+    } else {
+        format!("{size}B")
+    }
+}
+
+fn flags_names(flags: PartitionFlags) -> String {
+    let mut names = Vec::new();
+    if flags.encrypted() {
+        names.push("encrypted".into());
+    }
+    if flags.readonly() {
+        names.push("readonly".into());
+    }
+    if flags.unknown_bits() != 0 {
+        names.push(format!("{:#x}", flags.unknown_bits()));
+    }
+    names.join(":")
+}
+
+fn show(table: &Table) -> Result<(), Error> {
+    let mut out = io::stdout().lock();
+
+    writeln!(
+        out,
+        "{:<16} {:<5} {:<9} {:<10} {:<10} {:>7} Flags",
+        "Name", "Type", "SubType", "Offset", "End", "Size"
+    )?;
+
+    for partition in &table.partitions {
+        let (type_, subtype) = type_names(&partition.type_);
+        let line = format!(
+            "{:<16} {:<5} {:<9} {:#010x} {:#010x} {:>7} {}",
+            partition.name(),
+            type_,
+            subtype,
+            partition.offset,
+            partition.offset as usize + partition.size,
+            format_size(partition.size),
+            flags_names(partition.flags),
+        );
+        writeln!(out, "{}", line.trim_end())?;
+    }
+
+    match table.md5 {
+        Some(true) => writeln!(out, "MD5: valid")?,
+        Some(false) => writeln!(out, "MD5: invalid")?,
+        None => {}
+    }
+
+    Ok(())
+}
@@ -1,25 +1,50 @@
 use crate::{
     PartitionBuffer, PartitionEntry, PartitionError, PartitionReaderState, PartitionTable,
-    PartitionWriterState,
 };
-use core::{mem::MaybeUninit, ops::Deref};
-use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+use core::{
+    fmt,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut, Range},
+};
+use embedded_storage::nor_flash::{
+    ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
 
 /// Error type for embedded storage operations
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum NorFlashOpError<S: ReadNorFlash> {
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NorFlashOpError<S: ErrorType> {
     /// Partition specific error
     PartitionError(PartitionError),
     /// Storage specific error
     StorageError(S::Error),
 }
 
-impl<S: ReadNorFlash> From<PartitionError> for NorFlashOpError<S> {
+impl<S: ErrorType> From<PartitionError> for NorFlashOpError<S> {
     fn from(error: PartitionError) -> Self {
         Self::PartitionError(error)
     }
 }
 
+impl<S: ErrorType> fmt::Debug for NorFlashOpError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::PartitionError(error) => f.debug_tuple("PartitionError").field(error).finish(),
+            Self::StorageError(error) => f.debug_tuple("StorageError").field(error).finish(),
+        }
+    }
+}
+
+impl<S: ErrorType> NorFlashError for NorFlashOpError<S> {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Self::PartitionError(PartitionError::InvalidAlignment) => NorFlashErrorKind::NotAligned,
+            Self::PartitionError(PartitionError::OutOfBounds) => NorFlashErrorKind::OutOfBounds,
+            Self::PartitionError(_) => NorFlashErrorKind::Other,
+            Self::StorageError(error) => error.kind(),
+        }
+    }
+}
+
 impl PartitionTable {
     /// Get iterator over partitions from table
     ///
@@ -72,6 +97,14 @@ impl PartitionTable {
 
     /// Write partitions into table
     ///
+    /// The partitions layout is validated against storage capacity before writing.
+    /// The whole table is written with unused space padded by `0xff` like `gen_esp32part.py` does.
+    ///
+    /// The erase blocks which table occupies are read, updated, erased and programmed back
+    /// so the data outside of table is preserved. The blocks should fit into
+    /// [`PartitionTable::MAX_SIZE`], use [`PartitionTable::write_nor_flash_with_buffer`]
+    /// for storages with bigger erase size.
+    ///
     /// If `md5` feature isn't enabled `write_md5` argument will be ignored.
     #[cfg(feature = "embedded-storage")]
     pub fn write_nor_flash<S>(
@@ -83,52 +116,235 @@ impl PartitionTable {
     where
         S: NorFlash,
     {
-        // The following is not supported by the compiler
-        // (can't use generic parameters from outer function)
-        // const SECTOR_SIZE: usize = S::ERASE_SIZE;
-        const SECTOR_SIZE: usize = PartitionTable::MAX_SIZE;
-
-        let mut sector_data = MaybeUninit::<[u8; SECTOR_SIZE]>::uninit();
-        let sector_data = unsafe { sector_data.assume_init_mut() };
-        let mut data = &mut sector_data[..];
-        let mut state = PartitionWriterState::new(self.addr, self.size, write_md5);
-
-        for partition in partitions {
-            if state.is_done() {
-                return Err(PartitionError::TooManyData.into());
-            }
+        let mut buffer = MaybeUninit::<[u8; PartitionTable::MAX_SIZE]>::uninit();
+        let buffer = unsafe { buffer.assume_init_mut() };
+
+        self.write_nor_flash_with_buffer(storage, buffer, partitions, write_md5)
+    }
+
+    /// Write partitions into table using specified buffer
+    ///
+    /// The buffer should be large enough to hold all erase blocks which table occupies
+    /// otherwise [`PartitionError::NotEnoughSpace`] will be returned.
+    ///
+    /// See [`PartitionTable::write_nor_flash`] for details.
+    #[cfg(feature = "embedded-storage")]
+    pub fn write_nor_flash_with_buffer<S>(
+        &self,
+        storage: &mut S,
+        buffer: &mut [u8],
+        partitions: impl IntoIterator<Item = impl AsRef<PartitionEntry>>,
+        write_md5: bool,
+    ) -> Result<usize, NorFlashOpError<S>>
+    where
+        S: NorFlash,
+    {
+        let blocks = self.erase_blocks(
+            S::ERASE_SIZE,
+            S::WRITE_SIZE,
+            S::READ_SIZE,
+            storage.capacity(),
+        )?;
+        let buffer = buffer
+            .get_mut(..(blocks.end - blocks.start) as usize)
+            .ok_or(PartitionError::NotEnoughSpace)?;
+
+        storage
+            .read(blocks.start, buffer)
+            .map_err(NorFlashOpError::StorageError)?;
+
+        let table_data = &mut buffer[(self.addr - blocks.start) as usize..];
+        let size = self.write_bytes(table_data, partitions, write_md5)?;
+
+        self.check_bytes(&table_data[..size], storage.capacity())?;
+
+        storage
+            .erase(blocks.start, blocks.end)
+            .map_err(NorFlashOpError::StorageError)?;
+        storage
+            .write(blocks.start, buffer)
+            .map_err(NorFlashOpError::StorageError)?;
+
+        Ok(size)
+    }
+
+    /// Write partitions into table and verify result
+    ///
+    /// See [`PartitionTable::write_nor_flash`] and [`PartitionTable::verify_nor_flash`] for details.
+    #[cfg(feature = "embedded-storage")]
+    pub fn write_nor_flash_verified<S, I>(
+        &self,
+        storage: &mut S,
+        partitions: I,
+        write_md5: bool,
+    ) -> Result<usize, NorFlashOpError<S>>
+    where
+        S: NorFlash,
+        I: IntoIterator,
+        I::IntoIter: Clone,
+        I::Item: AsRef<PartitionEntry>,
+    {
+        let partitions = partitions.into_iter();
+        let size = self.write_nor_flash(storage, partitions.clone(), write_md5)?;
+        self.verify_nor_flash(storage, partitions, write_md5)?;
+        Ok(size)
+    }
+
+    /// Verify that table contains specified partitions
+    ///
+    /// The partitions are read back and compared one by one.
+    /// The first differing or unreadable entry is reported as [`PartitionError::VerifyFailed`].
+    /// When `check_md5` is set the MD5 entry should be present and valid.
+    ///
+    /// If `md5` feature isn't enabled `check_md5` argument will be ignored.
+    #[cfg(feature = "embedded-storage")]
+    pub fn verify_nor_flash<S>(
+        &self,
+        storage: &mut S,
+        partitions: impl IntoIterator<Item = impl AsRef<PartitionEntry>>,
+        check_md5: bool,
+    ) -> Result<(), NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        let mut iter = self.iter_nor_flash(storage, check_md5);
+        iter.set_strict(false);
 
-            let (head, rest) = data
-                .split_first_chunk_mut()
-                .ok_or(PartitionError::NotEnoughData)?;
+        let mut partitions = partitions.into_iter();
+        let mut index = 0;
 
-            state.write(head, partition)?;
+        loop {
+            let actual = match iter.next() {
+                Some(Ok(partition)) => Some(partition),
+                Some(Err(NorFlashOpError::PartitionError(_))) => {
+                    return Err(PartitionError::VerifyFailed(index).into());
+                }
+                Some(Err(error)) => return Err(error),
+                None => None,
+            };
 
-            data = rest;
+            match (actual, partitions.next()) {
+                (None, None) => break,
+                (Some(actual), Some(expected)) if actual == *expected.as_ref() => index += 1,
+                _ => return Err(PartitionError::VerifyFailed(index).into()),
+            }
         }
 
         #[cfg(feature = "md5")]
-        if write_md5 {
-            if state.is_done() {
-                return Err(PartitionError::TooManyData.into());
-            }
+        if check_md5 && iter.check_md5() != Some(true) {
+            return Err(PartitionError::VerifyFailed(index).into());
+        }
+
+        Ok(())
+    }
+
+    /// Update partitions in table using backup copy
+    ///
+    /// The partitions are written and verified in `backup` table first and then in this table.
+    /// When power is lost during update at least one of copies stays valid so
+    /// [`PartitionTable::read_nor_flash_with_backup`] reads either old or new partitions.
+    ///
+    /// The MD5 entry is always written. The tables should not share erase blocks
+    /// otherwise [`PartitionError::InvalidPartition`] will be returned.
+    /// The erase blocks of `backup` should not overlap new partitions or partitions which
+    /// are currently stored in this table. Such new partition is reported as layout violation
+    /// of kind [`OverlapsTable`](crate::ViolationKind::OverlapsTable) and stored one as
+    /// [`PartitionError::InvalidPartition`].
+    #[cfg(feature = "md5")]
+    pub fn update_nor_flash<S, I>(
+        &self,
+        storage: &mut S,
+        backup: &PartitionTable,
+        partitions: I,
+    ) -> Result<usize, NorFlashOpError<S>>
+    where
+        S: NorFlash,
+        I: IntoIterator,
+        I::IntoIter: Clone,
+        I::Item: AsRef<PartitionEntry>,
+    {
+        let blocks = self.erase_blocks(
+            S::ERASE_SIZE,
+            S::WRITE_SIZE,
+            S::READ_SIZE,
+            storage.capacity(),
+        )?;
+        let backup_blocks = backup.erase_blocks(
+            S::ERASE_SIZE,
+            S::WRITE_SIZE,
+            S::READ_SIZE,
+            storage.capacity(),
+        )?;
+        if blocks.start < backup_blocks.end && backup_blocks.start < blocks.end {
+            return Err(PartitionError::InvalidPartition.into());
+        }
 
-            let (head, rest) = data
-                .split_first_chunk_mut()
-                .ok_or(PartitionError::NotEnoughData)?;
+        // Erasing backup blocks should not destroy data of any partition
+        let (backup_addr, backup_size) = (
+            backup_blocks.start,
+            (backup_blocks.end - backup_blocks.start) as usize,
+        );
+        let partitions = partitions.into_iter();
+        if let Some(violation) = PartitionTable::new(backup_addr, backup_size)
+            .validate(partitions.clone(), storage.capacity())
+            .find(|violation| violation.kind == crate::ViolationKind::OverlapsTable)
+        {
+            return Err(PartitionError::InvalidLayout(violation).into());
+        }
+        let mut stored = self.iter_nor_flash(storage, false);
+        stored.set_strict(false);
+        if stored.map_while(Result::ok).any(|partition| {
+            crate::validate::overlaps(partition.offset, partition.size, backup_addr, backup_size)
+        }) {
+            return Err(PartitionError::InvalidPartition.into());
+        }
 
-            state.write_md5(head)?;
+        backup.write_nor_flash_verified(storage, partitions.clone(), true)?;
+        self.write_nor_flash_verified(storage, partitions, true)
+    }
 
-            data = rest;
+    /// Read partitions from table falling back to backup copy
+    ///
+    /// The MD5 checksum is mandatory for both copies. The backup is read when this table
+    /// is erased or corrupted. Storage errors are returned as is.
+    #[cfg(feature = "md5")]
+    pub fn read_nor_flash_with_backup<S, T>(
+        &self,
+        storage: &mut S,
+        backup: &PartitionTable,
+    ) -> Result<T, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+        T: FromIterator<PartitionEntry>,
+    {
+        match self.read_nor_flash(storage, Some(true)) {
+            Err(NorFlashOpError::PartitionError(_)) => backup.read_nor_flash(storage, Some(true)),
+            result => result,
         }
+    }
 
-        data.fill(0);
+    /// Get range of erase blocks which table occupies
+    pub(crate) fn erase_blocks(
+        &self,
+        erase_size: usize,
+        write_size: usize,
+        read_size: usize,
+        capacity: usize,
+    ) -> Result<Range<u32>, PartitionError> {
+        if erase_size == 0 || erase_size % write_size != 0 || erase_size % read_size != 0 {
+            return Err(PartitionError::InvalidAlignment);
+        }
 
-        storage
-            .write(0, sector_data)
-            .map_err(NorFlashOpError::StorageError)?;
+        let erase_size = erase_size as u32;
+        let start = self.addr - self.addr % erase_size;
+        let end = self
+            .addr
+            .checked_add(self.size as u32)
+            .and_then(|end| end.checked_next_multiple_of(erase_size))
+            .filter(|end| *end as usize <= capacity)
+            .ok_or(PartitionError::OutOfBounds)?;
 
-        Ok((state.offset() - self.addr) as usize)
+        Ok(start..end)
     }
 }
 
@@ -172,6 +388,12 @@ impl<S> Deref for PartitionNorFlashIter<'_, S> {
     }
 }
 
+impl<S> DerefMut for PartitionNorFlashIter<'_, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.state
+    }
+}
+
 impl<S> Iterator for PartitionNorFlashIter<'_, S>
 where
     S: ReadNorFlash,
@@ -194,3 +416,277 @@ where
             .transpose()
     }
 }
+
+#[cfg(all(test, feature = "md5"))]
+mod test {
+    use crate::*;
+    use embedded_storage::nor_flash::{NorFlash, NorFlashErrorKind};
+
+    extern crate std;
+    use std::{vec, vec::Vec};
+
+    type Flash<const ERASE_SIZE: usize> = NorFlashSim<Vec<u8>, 4, 4, ERASE_SIZE>;
+
+    /// Flash filled with garbage
+    fn garbage_flash<const ERASE_SIZE: usize>() -> Flash<ERASE_SIZE> {
+        NorFlashSim::new((0..0x400000).map(|index| (index % 251) as u8).collect())
+    }
+
+    const BIN: &[u8] = include_bytes!("../tests/partitions.bin");
+
+    fn partitions() -> Vec<PartitionEntry> {
+        BIN.chunks_exact(PartitionEntry::SIZE)
+            .map_while(|data| PartitionEntry::try_from(data).ok())
+            .collect()
+    }
+
+    fn check_flash<const ERASE_SIZE: usize>(flash: &mut Flash<ERASE_SIZE>) {
+        let initial = garbage_flash::<ERASE_SIZE>();
+        let table = PartitionTable::default();
+
+        assert_eq!(flash.data()[..0x8000], initial.data()[..0x8000]);
+        assert_eq!(flash.data()[0x8000..0x8c00], BIN[..]);
+        assert_eq!(flash.data()[0x8c00..0x9000], [0xff; 0x400]);
+        assert_eq!(flash.data()[0x9000..], initial.data()[0x9000..]);
+
+        assert_eq!(
+            table
+                .read_nor_flash::<_, Vec<_>>(flash, Some(true))
+                .unwrap(),
+            partitions()
+        );
+    }
+
+    #[test]
+    fn write_nor_flash() {
+        let table = PartitionTable::default();
+        let mut flash = garbage_flash::<0x1000>();
+
+        assert_eq!(
+            table.write_nor_flash(&mut flash, partitions(), true),
+            Ok(5 * PartitionEntry::SIZE)
+        );
+        check_flash(&mut flash);
+
+        // Rewrite requires erasing
+        let mut partitions = partitions();
+        partitions.pop();
+        table
+            .write_nor_flash(&mut flash, &partitions, true)
+            .unwrap();
+        table
+            .write_nor_flash(&mut flash, self::partitions(), true)
+            .unwrap();
+        check_flash(&mut flash);
+
+        let table = PartitionTable::new(0x3ff800, PartitionTable::MAX_SIZE);
+        assert_eq!(
+            table.write_nor_flash(&mut flash, &partitions, true),
+            Err(NorFlashOpError::PartitionError(PartitionError::OutOfBounds))
+        );
+    }
+
+    #[test]
+    fn write_nor_flash_big_erase() {
+        let table = PartitionTable::default();
+        let mut flash = garbage_flash::<0x10000>();
+
+        assert_eq!(
+            table.write_nor_flash(&mut flash, partitions(), true),
+            Err(NorFlashOpError::PartitionError(
+                PartitionError::NotEnoughSpace
+            ))
+        );
+        assert_eq!(flash.data(), garbage_flash::<0x10000>().data());
+
+        let mut buffer = vec![0; 0x10000];
+        assert_eq!(
+            table.write_nor_flash_with_buffer(&mut flash, &mut buffer, partitions(), true),
+            Ok(5 * PartitionEntry::SIZE)
+        );
+        check_flash(&mut flash);
+    }
+
+    #[test]
+    fn verify_nor_flash() {
+        let table = PartitionTable::default();
+        let partitions = partitions();
+        let mut flash = garbage_flash::<0x1000>();
+
+        table
+            .write_nor_flash(&mut flash, &partitions, true)
+            .unwrap();
+        assert_eq!(
+            table.verify_nor_flash(&mut flash, &partitions, true),
+            Ok(())
+        );
+        assert_eq!(
+            table.verify_nor_flash(&mut flash, &partitions[..4], true),
+            Err(NorFlashOpError::PartitionError(
+                PartitionError::VerifyFailed(4)
+            ))
+        );
+
+        // Name of third partition
+        flash.data_mut()[0x8000 + 2 * PartitionEntry::SIZE + 12] ^= 1;
+        assert_eq!(
+            table.verify_nor_flash(&mut flash, &partitions, false),
+            Err(NorFlashOpError::PartitionError(
+                PartitionError::VerifyFailed(2)
+            ))
+        );
+
+        // MD5 entry
+        let mut flash = garbage_flash::<0x1000>();
+        flash.inject_stuck_byte(0x8000 + 5 * PartitionEntry::SIZE + 0x1f);
+        assert_eq!(
+            table.write_nor_flash_verified(&mut flash, &partitions, true),
+            Err(NorFlashOpError::PartitionError(
+                PartitionError::VerifyFailed(5)
+            ))
+        );
+        assert_eq!(
+            table.verify_nor_flash(&mut flash, &partitions, false),
+            Ok(())
+        );
+
+        // Magic of first partition
+        let mut flash = garbage_flash::<0x1000>();
+        flash.inject_stuck_byte(0x8000);
+        assert_eq!(
+            table.write_nor_flash_verified(&mut flash, &partitions, true),
+            Err(NorFlashOpError::PartitionError(
+                PartitionError::VerifyFailed(0)
+            ))
+        );
+
+        let mut flash = garbage_flash::<0x1000>();
+        assert_eq!(
+            table.write_nor_flash_verified(&mut flash, &partitions, true),
+            Ok(5 * PartitionEntry::SIZE)
+        );
+        check_flash(&mut flash);
+    }
+
+    #[test]
+    fn update_nor_flash() {
+        let table = PartitionTable::default();
+        let backup = PartitionTable::new(0x9000, PartitionTable::MAX_SIZE);
+
+        let old = [
+            PartitionEntry::new(DataPartitionType::Nvs, 0xa000, 0x6000, "nvs", false).unwrap(),
+            PartitionEntry::new(
+                AppPartitionType::Factory,
+                0x10000,
+                0x10000,
+                "factory",
+                false,
+            )
+            .unwrap(),
+        ];
+        let new = [
+            PartitionEntry::new(DataPartitionType::Nvs, 0xa000, 0x4000, "nvs", false).unwrap(),
+            PartitionEntry::new(DataPartitionType::Ota, 0xe000, 0x2000, "otadata", false).unwrap(),
+            PartitionEntry::new(AppPartitionType::Ota(0), 0x10000, 0x10000, "ota_0", false)
+                .unwrap(),
+        ];
+
+        let mut initial = NorFlashSim::<_, 4, 4>::new(vec![0xff; 0x20000]);
+        table.write_nor_flash(&mut initial, &old, true).unwrap();
+
+        assert_eq!(
+            table.update_nor_flash(&mut initial, &PartitionTable::new(0x8800, 0x800), &new),
+            Err(NorFlashOpError::PartitionError(
+                PartitionError::InvalidPartition
+            ))
+        );
+
+        // Backup overlaps new otadata partition
+        let mut flash = initial.clone();
+        assert_eq!(
+            table.update_nor_flash(&mut flash, &PartitionTable::new(0xe000, 0x1000), &new),
+            Err(NorFlashOpError::PartitionError(
+                PartitionError::InvalidLayout(PartitionViolation {
+                    index: 1,
+                    kind: ViolationKind::OverlapsTable,
+                })
+            ))
+        );
+        // Backup overlaps stored nvs partition
+        assert_eq!(
+            table.update_nor_flash(&mut flash, &PartitionTable::new(0xe000, 0x1000), &new[..1]),
+            Err(NorFlashOpError::PartitionError(
+                PartitionError::InvalidPartition
+            ))
+        );
+        assert_eq!(flash, initial);
+
+        assert_eq!(
+            table.update_nor_flash(&mut flash, &backup, &new),
+            Ok(3 * PartitionEntry::SIZE)
+        );
+        let total = flash.written() - initial.written();
+
+        for cut in (0..total).step_by(16) {
+            let mut flash = initial.clone();
+            flash.power_cut_after(cut);
+            assert_eq!(
+                table.update_nor_flash(&mut flash, &backup, &new),
+                Err(NorFlashOpError::StorageError(NorFlashErrorKind::Other))
+            );
+            flash.power_on();
+
+            let partitions: Vec<PartitionEntry> = table
+                .read_nor_flash_with_backup(&mut flash, &backup)
+                .unwrap();
+            assert!(partitions == old || partitions == new, "cut at {cut}");
+
+            table.update_nor_flash(&mut flash, &backup, &new).unwrap();
+            assert_eq!(
+                table.read_nor_flash::<_, Vec<_>>(&mut flash, Some(true)),
+                Ok(new.to_vec())
+            );
+        }
+    }
+
+    #[test]
+    fn read_nor_flash_with_backup() {
+        let table = PartitionTable::default();
+        let backup = PartitionTable::new(0x3ff000, PartitionTable::MAX_SIZE);
+        let partitions = partitions();
+
+        let mut flash = NorFlashSim::<_>::new(vec![0xff; 0x400000]);
+        table
+            .update_nor_flash(&mut flash, &backup, &partitions)
+            .unwrap();
+
+        // Erased primary table
+        flash.erase(0x8000, 0x9000).unwrap();
+        assert_eq!(
+            table.read_nor_flash_with_backup::<_, Vec<_>>(&mut flash, &backup),
+            Ok(partitions.clone())
+        );
+
+        // Corrupted primary table
+        table
+            .write_nor_flash(&mut flash, &partitions, true)
+            .unwrap();
+        flash.data_mut()[0x8000 + 0x10] ^= 1;
+        assert_eq!(
+            table.read_nor_flash_with_backup::<_, Vec<_>>(&mut flash, &backup),
+            Ok(partitions.clone())
+        );
+
+        flash.data_mut()[0x3ff000 + 0x10] ^= 1;
+        assert_eq!(
+            table.read_nor_flash_with_backup::<_, Vec<_>>(&mut flash, &backup),
+            Err(NorFlashOpError::PartitionError(PartitionError::InvalidMd5))
+        );
+
+        flash.inject_error(NorFlashErrorKind::Other);
+        assert_eq!(
+            table.read_nor_flash_with_backup::<_, Vec<_>>(&mut flash, &backup),
+            Err(NorFlashOpError::StorageError(NorFlashErrorKind::Other))
+        );
+    }
+}
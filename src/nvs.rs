@@ -0,0 +1,2245 @@
+use crate::{utils, DataPartitionType, PartitionEntry, PartitionError, PartitionType};
+
+#[cfg(feature = "embedded-storage")]
+use crate::NorFlashOpError;
+
+#[cfg(feature = "aes")]
+use crate::NvsKeys;
+
+#[cfg(all(feature = "aes", feature = "embedded-storage"))]
+use crate::NvsCipher;
+
+#[cfg(feature = "embedded-storage")]
+use embedded_storage::nor_flash::{MultiwriteNorFlash, NorFlash, ReadNorFlash};
+
+/// NVS page state
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum NvsPageState {
+    /// Page is erased
+    #[default]
+    Uninitialized,
+
+    /// Page is used for writing
+    Active,
+
+    /// Page is full
+    Full,
+
+    /// Page is being moved
+    Freeing,
+
+    /// Page is corrupted
+    Corrupt,
+
+    /// Page is not a valid
+    Invalid,
+
+    /// Unknown state value
+    Other(u32),
+}
+
+impl From<u32> for NvsPageState {
+    fn from(raw: u32) -> Self {
+        match raw {
+            0xffffffff => Self::Uninitialized,
+            0xfffffffe => Self::Active,
+            0xfffffffc => Self::Full,
+            0xfffffff8 => Self::Freeing,
+            0xfffffff0 => Self::Corrupt,
+            0x00000000 => Self::Invalid,
+            _ => Self::Other(raw),
+        }
+    }
+}
+
+impl From<NvsPageState> for u32 {
+    fn from(state: NvsPageState) -> Self {
+        use NvsPageState::*;
+        match state {
+            Uninitialized => 0xffffffff,
+            Active => 0xfffffffe,
+            Full => 0xfffffffc,
+            Freeing => 0xfffffff8,
+            Corrupt => 0xfffffff0,
+            Invalid => 0x00000000,
+            Other(raw) => raw,
+        }
+    }
+}
+
+/// NVS page header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NvsPageHeader {
+    /// Page state
+    pub state: NvsPageState,
+
+    /// Page sequence number
+    pub seq: u32,
+
+    /// Format version
+    pub version: u8,
+
+    /// Reserved data
+    pub reserved: [u8; 19],
+
+    /// CRC32 of sequence number, version and reserved data
+    pub crc: u32,
+}
+
+impl Default for NvsPageHeader {
+    /// Erased header
+    fn default() -> Self {
+        Self {
+            state: NvsPageState::Uninitialized,
+            seq: u32::MAX,
+            version: 0xff,
+            reserved: [0xff; 19],
+            crc: u32::MAX,
+        }
+    }
+}
+
+impl NvsPageHeader {
+    /// Size of header in bytes
+    pub const SIZE: usize = 32;
+
+    /// Format version without multi-page blobs support
+    pub const VERSION1: u8 = 0xff;
+
+    /// Format version with multi-page blobs support
+    pub const VERSION2: u8 = 0xfe;
+
+    /// Instantiate header with valid CRC
+    pub fn new(state: NvsPageState, seq: u32, version: u8) -> Self {
+        let mut header = Self {
+            state,
+            seq,
+            version,
+            ..Default::default()
+        };
+        header.crc = header.calc_crc();
+        header
+    }
+
+    /// Compute CRC32 of header
+    pub fn calc_crc(&self) -> u32 {
+        let mut data = [0; Self::SIZE];
+        self.to_bytes(&mut data);
+        utils::crc32_le(u32::MAX, &data[4..28])
+    }
+
+    /// Check that header is initialized and has correct CRC
+    pub fn is_valid(&self) -> bool {
+        !matches!(
+            self.state,
+            NvsPageState::Uninitialized | NvsPageState::Corrupt | NvsPageState::Invalid
+        ) && self.crc == self.calc_crc()
+    }
+
+    /// Convert header from binary representation
+    pub fn from_bytes(data: &[u8; Self::SIZE]) -> Self {
+        let (state, data) = data.split_first_chunk::<4>().unwrap();
+        let (seq, data) = data.split_first_chunk::<4>().unwrap();
+        let (version, data) = data.split_first_chunk::<1>().unwrap();
+        let (reserved, data) = data.split_first_chunk().unwrap();
+        let (crc, _) = data.split_first_chunk::<4>().unwrap();
+
+        Self {
+            state: u32::from_le_bytes(*state).into(),
+            seq: u32::from_le_bytes(*seq),
+            version: version[0],
+            reserved: *reserved,
+            crc: u32::from_le_bytes(*crc),
+        }
+    }
+
+    /// Convert header to binary representation
+    pub fn to_bytes(&self, data: &mut [u8; Self::SIZE]) {
+        let (state, data) = data.split_first_chunk_mut::<4>().unwrap();
+        *state = u32::from(self.state).to_le_bytes();
+        let (seq, data) = data.split_first_chunk_mut::<4>().unwrap();
+        *seq = self.seq.to_le_bytes();
+        let (version, data) = data.split_first_chunk_mut::<1>().unwrap();
+        version[0] = self.version;
+        let (reserved, data) = data.split_first_chunk_mut().unwrap();
+        *reserved = self.reserved;
+        let (crc, _) = data.split_first_chunk_mut::<4>().unwrap();
+        *crc = self.crc.to_le_bytes();
+    }
+}
+
+/// NVS entry state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NvsEntryState {
+    /// Entry is free
+    Empty,
+
+    /// Entry is written
+    Written,
+
+    /// Entry is erased
+    Erased,
+
+    /// Entry state is not a valid
+    Illegal,
+}
+
+/// NVS page entries state bitmap
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NvsEntryStates(pub [u8; 32]);
+
+impl Default for NvsEntryStates {
+    /// All entries are empty
+    fn default() -> Self {
+        Self([0xff; 32])
+    }
+}
+
+impl NvsEntryStates {
+    /// Get state of entry
+    pub fn get(&self, index: usize) -> NvsEntryState {
+        match (self.0[index / 4] >> (index % 4 * 2)) & 0b11 {
+            0b11 => NvsEntryState::Empty,
+            0b10 => NvsEntryState::Written,
+            0b00 => NvsEntryState::Erased,
+            _ => NvsEntryState::Illegal,
+        }
+    }
+
+    /// Set state of entry
+    pub fn set(&mut self, index: usize, state: NvsEntryState) {
+        let bits = match state {
+            NvsEntryState::Empty => 0b11,
+            NvsEntryState::Written => 0b10,
+            NvsEntryState::Erased => 0b00,
+            NvsEntryState::Illegal => 0b01,
+        };
+        let shift = index % 4 * 2;
+        let byte = &mut self.0[index / 4];
+        *byte = (*byte & !(0b11 << shift)) | (bits << shift);
+    }
+}
+
+/// NVS item type
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum NvsType {
+    /// Unsigned 8-bit integer
+    U8,
+
+    /// Signed 8-bit integer
+    I8,
+
+    /// Unsigned 16-bit integer
+    U16,
+
+    /// Signed 16-bit integer
+    I16,
+
+    /// Unsigned 32-bit integer
+    U32,
+
+    /// Signed 32-bit integer
+    I32,
+
+    /// Unsigned 64-bit integer
+    U64,
+
+    /// Signed 64-bit integer
+    I64,
+
+    /// Zero-terminated string
+    Str,
+
+    /// Single-page blob (version 1 format)
+    Blob,
+
+    /// Chunk of multi-page blob
+    BlobData,
+
+    /// Index of multi-page blob
+    BlobIndex,
+
+    /// Any type
+    #[default]
+    Any,
+
+    /// Unknown type
+    Other(u8),
+}
+
+impl From<u8> for NvsType {
+    fn from(raw: u8) -> Self {
+        match raw {
+            0x01 => Self::U8,
+            0x11 => Self::I8,
+            0x02 => Self::U16,
+            0x12 => Self::I16,
+            0x04 => Self::U32,
+            0x14 => Self::I32,
+            0x08 => Self::U64,
+            0x18 => Self::I64,
+            0x21 => Self::Str,
+            0x41 => Self::Blob,
+            0x42 => Self::BlobData,
+            0x48 => Self::BlobIndex,
+            0xff => Self::Any,
+            _ => Self::Other(raw),
+        }
+    }
+}
+
+impl From<NvsType> for u8 {
+    fn from(ty: NvsType) -> Self {
+        use NvsType::*;
+        match ty {
+            U8 => 0x01,
+            I8 => 0x11,
+            U16 => 0x02,
+            I16 => 0x12,
+            U32 => 0x04,
+            I32 => 0x14,
+            U64 => 0x08,
+            I64 => 0x18,
+            Str => 0x21,
+            Blob => 0x41,
+            BlobData => 0x42,
+            BlobIndex => 0x48,
+            Any => 0xff,
+            Other(raw) => raw,
+        }
+    }
+}
+
+/// NVS value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NvsValue<'a> {
+    /// Unsigned 8-bit integer
+    U8(u8),
+
+    /// Signed 8-bit integer
+    I8(i8),
+
+    /// Unsigned 16-bit integer
+    U16(u16),
+
+    /// Signed 16-bit integer
+    I16(i16),
+
+    /// Unsigned 32-bit integer
+    U32(u32),
+
+    /// Signed 32-bit integer
+    I32(i32),
+
+    /// Unsigned 64-bit integer
+    U64(u64),
+
+    /// Signed 64-bit integer
+    I64(i64),
+
+    /// String
+    Str(&'a str),
+
+    /// Binary data
+    Blob(&'a [u8]),
+}
+
+impl NvsValue<'_> {
+    /// Get type of value
+    ///
+    /// Binary data is stored as multi-page blob.
+    pub fn type_(&self) -> NvsType {
+        match self {
+            Self::U8(_) => NvsType::U8,
+            Self::I8(_) => NvsType::I8,
+            Self::U16(_) => NvsType::U16,
+            Self::I16(_) => NvsType::I16,
+            Self::U32(_) => NvsType::U32,
+            Self::I32(_) => NvsType::I32,
+            Self::U64(_) => NvsType::U64,
+            Self::I64(_) => NvsType::I64,
+            Self::Str(_) => NvsType::Str,
+            Self::Blob(_) => NvsType::BlobIndex,
+        }
+    }
+
+    /// Get data of primitive value as stored in item
+    ///
+    /// Returns `None` for variable length values.
+    pub fn to_item_data(&self) -> Option<[u8; 8]> {
+        let mut data = [0xff; 8];
+        match self {
+            Self::U8(value) => data[..1].copy_from_slice(&value.to_le_bytes()),
+            Self::I8(value) => data[..1].copy_from_slice(&value.to_le_bytes()),
+            Self::U16(value) => data[..2].copy_from_slice(&value.to_le_bytes()),
+            Self::I16(value) => data[..2].copy_from_slice(&value.to_le_bytes()),
+            Self::U32(value) => data[..4].copy_from_slice(&value.to_le_bytes()),
+            Self::I32(value) => data[..4].copy_from_slice(&value.to_le_bytes()),
+            Self::U64(value) => data = value.to_le_bytes(),
+            Self::I64(value) => data = value.to_le_bytes(),
+            Self::Str(_) | Self::Blob(_) => return None,
+        }
+        Some(data)
+    }
+}
+
+/// NVS item
+///
+/// The single 32-byte entry which is either a value or a header of variable length data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NvsItem {
+    /// Namespace index (zero for namespace items)
+    pub ns: u8,
+
+    /// Item type
+    pub type_: NvsType,
+
+    /// Number of entries occupied by item including data
+    pub span: u8,
+
+    /// Index of blob chunk
+    pub chunk_index: u8,
+
+    /// CRC32 of item
+    pub crc: u32,
+
+    /// Key bytes
+    pub key: [u8; NvsItem::KEY_SIZE],
+
+    /// Value or data description
+    pub data: [u8; 8],
+}
+
+impl NvsItem {
+    /// Size of item in bytes
+    pub const SIZE: usize = 32;
+
+    /// Size of key field
+    pub const KEY_SIZE: usize = 16;
+
+    /// Maximum length of key
+    pub const MAX_KEY_LEN: usize = Self::KEY_SIZE - 1;
+
+    /// Chunk index of items which is not a blob chunks
+    pub const CHUNK_ANY: u8 = 0xff;
+
+    /// Instantiate item with specified namespace index, type and key
+    ///
+    /// The key should be non-empty and not longer than [`NvsItem::MAX_KEY_LEN`].
+    pub fn new(ns: u8, type_: NvsType, key: &str) -> Result<Self, PartitionError> {
+        if key.is_empty() || key.len() > Self::MAX_KEY_LEN {
+            return Err(PartitionError::InvalidString);
+        }
+        let mut key_data = [0; Self::KEY_SIZE];
+        key_data[..key.len()].copy_from_slice(key.as_bytes());
+
+        let mut item = Self {
+            ns,
+            type_,
+            span: 1,
+            chunk_index: Self::CHUNK_ANY,
+            crc: 0,
+            key: key_data,
+            data: [0xff; 8],
+        };
+        item.crc = item.calc_crc();
+        Ok(item)
+    }
+
+    /// Compute CRC32 of item
+    pub fn calc_crc(&self) -> u32 {
+        let mut data = [0; Self::SIZE];
+        self.to_bytes(&mut data);
+        let crc = utils::crc32_le(u32::MAX, &data[..4]);
+        utils::crc32_le(crc, &data[8..])
+    }
+
+    /// Check item CRC
+    pub fn is_valid(&self) -> bool {
+        self.crc == self.calc_crc()
+    }
+
+    /// Item is a namespace definition
+    pub fn is_namespace(&self) -> bool {
+        self.ns == 0 && self.type_ == NvsType::U8
+    }
+
+    /// Get key
+    pub fn key(&self) -> &str {
+        utils::str_lossy(&self.key)
+    }
+
+    /// Get size of variable length data
+    pub fn data_size(&self) -> usize {
+        u16::from_le_bytes([self.data[0], self.data[1]]) as usize
+    }
+
+    /// Get CRC32 of variable length data
+    pub fn data_crc(&self) -> u32 {
+        u32::from_le_bytes([self.data[4], self.data[5], self.data[6], self.data[7]])
+    }
+
+    /// Get multi-page blob size, number of chunks and index of first chunk
+    pub fn blob_index(&self) -> (usize, u8, u8) {
+        let size = u32::from_le_bytes([self.data[0], self.data[1], self.data[2], self.data[3]]);
+        (size as usize, self.data[4], self.data[5])
+    }
+
+    /// Get primitive value
+    ///
+    /// Returns `None` for variable length items.
+    pub fn value(&self) -> Option<NvsValue<'static>> {
+        let data = self.data;
+        Some(match self.type_ {
+            NvsType::U8 => NvsValue::U8(data[0]),
+            NvsType::I8 => NvsValue::I8(data[0] as i8),
+            NvsType::U16 => NvsValue::U16(u16::from_le_bytes([data[0], data[1]])),
+            NvsType::I16 => NvsValue::I16(i16::from_le_bytes([data[0], data[1]])),
+            NvsType::U32 => NvsValue::U32(u32::from_le_bytes(*data.first_chunk().unwrap())),
+            NvsType::I32 => NvsValue::I32(i32::from_le_bytes(*data.first_chunk().unwrap())),
+            NvsType::U64 => NvsValue::U64(u64::from_le_bytes(data)),
+            NvsType::I64 => NvsValue::I64(i64::from_le_bytes(data)),
+            _ => return None,
+        })
+    }
+
+    /// Set span, data description and CRC for item followed by data
+    ///
+    /// The data is concatenation of specified parts.
+    pub(crate) fn seal(&mut self, data: &[&[u8]]) {
+        let size = data.iter().map(|part| part.len()).sum::<usize>();
+
+        if matches!(self.type_, NvsType::Str | NvsType::Blob | NvsType::BlobData) {
+            let crc = data
+                .iter()
+                .fold(u32::MAX, |crc, part| utils::crc32_le(crc, part));
+            self.data[..2].copy_from_slice(&(size as u16).to_le_bytes());
+            self.data[2..4].fill(0xff);
+            self.data[4..].copy_from_slice(&crc.to_le_bytes());
+        }
+        self.span = (1 + size.div_ceil(Self::SIZE)) as u8;
+        self.crc = self.calc_crc();
+    }
+
+    /// Get entries of item followed by data
+    ///
+    /// The tail of data is padded with `0xff`.
+    pub(crate) fn entries<'d>(
+        &self,
+        data: &'d [&'d [u8]],
+    ) -> impl Iterator<Item = [u8; Self::SIZE]> + 'd {
+        let mut entry = [0; Self::SIZE];
+        self.to_bytes(&mut entry);
+
+        let mut bytes = data.iter().flat_map(|part| part.iter());
+        let chunks = (1..self.span).map(move |_| {
+            let mut entry = [0xff; Self::SIZE];
+            for (dst, src) in entry.iter_mut().zip(&mut bytes) {
+                *dst = *src;
+            }
+            entry
+        });
+
+        core::iter::once(entry).chain(chunks)
+    }
+
+    /// Convert item from binary representation
+    pub fn from_bytes(data: &[u8; Self::SIZE]) -> Self {
+        let (crc, rest) = data[4..].split_first_chunk::<4>().unwrap();
+        let (key, rest) = rest.split_first_chunk().unwrap();
+
+        Self {
+            ns: data[0],
+            type_: data[1].into(),
+            span: data[2],
+            chunk_index: data[3],
+            crc: u32::from_le_bytes(*crc),
+            key: *key,
+            data: *rest.first_chunk().unwrap(),
+        }
+    }
+
+    /// Convert item to binary representation
+    pub fn to_bytes(&self, data: &mut [u8; Self::SIZE]) {
+        data[0] = self.ns;
+        data[1] = self.type_.into();
+        data[2] = self.span;
+        data[3] = self.chunk_index;
+        data[4..8].copy_from_slice(&self.crc.to_le_bytes());
+        data[8..24].copy_from_slice(&self.key);
+        data[24..].copy_from_slice(&self.data);
+    }
+}
+
+/// Item found in NVS partition
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NvsEntry {
+    /// Index of page in partition
+    pub page: usize,
+
+    /// Page sequence number
+    pub seq: u32,
+
+    /// Index of entry in page
+    pub index: usize,
+
+    /// Item
+    pub item: NvsItem,
+}
+
+#[cfg(feature = "embedded-storage")]
+impl NvsEntry {
+    /// Check that entry was written after other one
+    fn is_newer(&self, other: &Self) -> bool {
+        (self.seq, self.index) > (other.seq, other.index)
+    }
+}
+
+/// NVS partition
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NvsPartition {
+    /// Address of partition
+    pub offset: u32,
+
+    /// Size of partition
+    pub size: usize,
+
+    /// Keys to encrypt entries
+    #[cfg(feature = "aes")]
+    keys: Option<NvsKeys>,
+}
+
+impl TryFrom<&PartitionEntry> for NvsPartition {
+    type Error = PartitionError;
+
+    fn try_from(partition: &PartitionEntry) -> Result<Self, Self::Error> {
+        if partition.type_ != PartitionType::Data(DataPartitionType::Nvs) {
+            return Err(PartitionError::InvalidPartition);
+        }
+        Ok(Self::new(partition.offset, partition.size))
+    }
+}
+
+impl NvsPartition {
+    /// Size of page
+    pub const PAGE_SIZE: usize = 4096;
+
+    /// Number of entries in page
+    pub const ENTRY_COUNT: usize = 126;
+
+    /// Offset of entry states bitmap in page
+    const STATES_OFFSET: usize = NvsPageHeader::SIZE;
+
+    /// Offset of first entry in page
+    const ENTRIES_OFFSET: usize = Self::STATES_OFFSET + 32;
+
+    /// Instantiate partition with specified address and size
+    pub fn new(offset: u32, size: usize) -> Self {
+        Self {
+            offset,
+            size,
+            #[cfg(feature = "aes")]
+            keys: None,
+        }
+    }
+
+    /// Use keys to encrypt and decrypt entries
+    ///
+    /// Page headers and entry states are not encrypted.
+    #[cfg(feature = "aes")]
+    pub fn with_keys(self, keys: NvsKeys) -> Self {
+        Self {
+            keys: Some(keys),
+            ..self
+        }
+    }
+
+    /// Get keys which is used to encrypt entries
+    #[cfg(feature = "aes")]
+    pub fn keys(&self) -> Option<&NvsKeys> {
+        self.keys.as_ref()
+    }
+
+    /// Get number of pages
+    pub fn page_count(&self) -> usize {
+        self.size / Self::PAGE_SIZE
+    }
+
+    /// Get address of page header
+    pub fn page_addr(&self, page: usize) -> u32 {
+        self.offset + (page * Self::PAGE_SIZE) as u32
+    }
+
+    /// Get address of entry
+    pub fn entry_addr(&self, page: usize, index: usize) -> u32 {
+        self.page_addr(page) + (Self::ENTRIES_OFFSET + index * NvsItem::SIZE) as u32
+    }
+
+    /// Get address of page entry states
+    pub fn states_addr(&self, page: usize) -> u32 {
+        self.page_addr(page) + Self::STATES_OFFSET as u32
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl NvsPartition {
+    /// Get iterator over written items
+    ///
+    /// Items with invalid CRC and pages with invalid headers are skipped.
+    pub fn iter_nor_flash<'s, S>(&self, storage: &'s mut S) -> NvsNorFlashIter<'s, S>
+    where
+        S: ReadNorFlash,
+    {
+        NvsNorFlashIter {
+            storage,
+            partition: *self,
+            page: 0,
+            index: NvsPartition::ENTRY_COUNT,
+            seq: 0,
+            states: NvsEntryStates::default(),
+        }
+    }
+
+    /// Get cipher for entries when partition is encrypted
+    #[cfg(feature = "aes")]
+    fn cipher(&self) -> Option<NvsCipher> {
+        self.keys.as_ref().map(NvsCipher::new)
+    }
+
+    fn read_header<S>(
+        &self,
+        storage: &mut S,
+        page: usize,
+    ) -> Result<NvsPageHeader, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        let mut data = [0; NvsPageHeader::SIZE];
+        storage
+            .read(self.page_addr(page), &mut data)
+            .map_err(NorFlashOpError::StorageError)?;
+        Ok(NvsPageHeader::from_bytes(&data))
+    }
+
+    fn read_states<S>(
+        &self,
+        storage: &mut S,
+        page: usize,
+    ) -> Result<NvsEntryStates, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        let mut states = NvsEntryStates::default();
+        storage
+            .read(self.states_addr(page), &mut states.0)
+            .map_err(NorFlashOpError::StorageError)?;
+        Ok(states)
+    }
+
+    fn read_entry<S>(
+        &self,
+        storage: &mut S,
+        page: usize,
+        index: usize,
+    ) -> Result<[u8; NvsItem::SIZE], NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        let addr = self.entry_addr(page, index);
+        let mut data = [0; NvsItem::SIZE];
+        storage
+            .read(addr, &mut data)
+            .map_err(NorFlashOpError::StorageError)?;
+        #[cfg(feature = "aes")]
+        if let Some(cipher) = self.cipher() {
+            cipher.decrypt(addr - self.offset, &mut data);
+        }
+        Ok(data)
+    }
+
+    /// Find first valid item in page starting from specified entry
+    fn next_item<S>(
+        &self,
+        storage: &mut S,
+        page: usize,
+        states: &NvsEntryStates,
+        mut index: usize,
+    ) -> Result<Option<(usize, NvsItem)>, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        while index < Self::ENTRY_COUNT {
+            if states.get(index) != NvsEntryState::Written {
+                index += 1;
+                continue;
+            }
+
+            let item = NvsItem::from_bytes(&self.read_entry(storage, page, index)?);
+            let end = index + item.span as usize;
+            if !item.is_valid()
+                || end == index
+                || end > Self::ENTRY_COUNT
+                || (index + 1..end).any(|index| states.get(index) != NvsEntryState::Written)
+            {
+                index += 1;
+                continue;
+            }
+
+            return Ok(Some((index, item)));
+        }
+
+        Ok(None)
+    }
+
+    /// Find the latest written item which satisfies predicate
+    fn find_nor_flash<S>(
+        &self,
+        storage: &mut S,
+        mut predicate: impl FnMut(&NvsItem) -> bool,
+    ) -> Result<Option<NvsEntry>, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        let mut found: Option<NvsEntry> = None;
+
+        for entry in self.iter_nor_flash(storage) {
+            let entry = entry?;
+            if predicate(&entry.item) && found.map_or(true, |found| entry.is_newer(&found)) {
+                found = Some(entry);
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Find namespace index by name
+    pub fn find_namespace_nor_flash<S>(
+        &self,
+        storage: &mut S,
+        name: &str,
+    ) -> Result<Option<u8>, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        Ok(self
+            .find_nor_flash(storage, |item| item.is_namespace() && item.key() == name)?
+            .map(|entry| entry.item.data[0]))
+    }
+
+    /// Read variable length data of string, blob or blob chunk
+    ///
+    /// The data CRC is checked.
+    pub fn read_data_nor_flash<'b, S>(
+        &self,
+        storage: &mut S,
+        entry: &NvsEntry,
+        buffer: &'b mut [u8],
+    ) -> Result<&'b [u8], NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        let size = self.read_data_into(storage, entry, buffer)?;
+        Ok(&buffer[..size])
+    }
+
+    fn read_data_into<S>(
+        &self,
+        storage: &mut S,
+        entry: &NvsEntry,
+        buffer: &mut [u8],
+    ) -> Result<usize, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        let item = &entry.item;
+        if !matches!(item.type_, NvsType::Str | NvsType::Blob | NvsType::BlobData) {
+            return Err(PartitionError::InvalidPartition.into());
+        }
+
+        let size = item.data_size();
+        if size > (item.span as usize - 1) * NvsItem::SIZE {
+            return Err(PartitionError::NotEnoughData.into());
+        }
+        let buffer = buffer.get_mut(..size).ok_or(PartitionError::TooManyData)?;
+
+        for (index, chunk) in buffer.chunks_mut(NvsItem::SIZE).enumerate() {
+            let data = self.read_entry(storage, entry.page, entry.index + 1 + index)?;
+            chunk.copy_from_slice(&data[..chunk.len()]);
+        }
+
+        if utils::crc32_le(u32::MAX, buffer) != item.data_crc() {
+            return Err(PartitionError::InvalidChecksum.into());
+        }
+
+        Ok(size)
+    }
+
+    /// Get value by namespace and key
+    ///
+    /// The `buffer` is used to hold the data of strings and blobs.
+    pub fn get_nor_flash<'b, S>(
+        &self,
+        storage: &mut S,
+        namespace: &str,
+        key: &str,
+        buffer: &'b mut [u8],
+    ) -> Result<Option<NvsValue<'b>>, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        let Some(ns) = self.find_namespace_nor_flash(storage, namespace)? else {
+            return Ok(None);
+        };
+
+        let Some(entry) = self.find_nor_flash(storage, |item| {
+            item.ns == ns && item.type_ != NvsType::BlobData && item.key() == key
+        })?
+        else {
+            return Ok(None);
+        };
+
+        if let Some(value) = entry.item.value() {
+            return Ok(Some(value));
+        }
+
+        Ok(Some(match entry.item.type_ {
+            NvsType::Str => {
+                let size = self.read_data_into(storage, &entry, buffer)?;
+                let data = &buffer[..size];
+                let data = data.strip_suffix(b"\0").unwrap_or(data);
+                NvsValue::Str(
+                    core::str::from_utf8(data).map_err(|_| PartitionError::InvalidString)?,
+                )
+            }
+            NvsType::Blob => {
+                let size = self.read_data_into(storage, &entry, buffer)?;
+                NvsValue::Blob(&buffer[..size])
+            }
+            NvsType::BlobIndex => {
+                let (size, chunk_count, chunk_start) = entry.item.blob_index();
+                if size > buffer.len() {
+                    return Err(PartitionError::TooManyData.into());
+                }
+
+                let mut offset = 0;
+                for chunk_index in (0..chunk_count).map(|index| chunk_start.wrapping_add(index)) {
+                    let chunk = self
+                        .find_nor_flash(storage, |item| {
+                            item.ns == ns
+                                && item.type_ == NvsType::BlobData
+                                && item.chunk_index == chunk_index
+                                && item.key() == key
+                        })?
+                        .ok_or(PartitionError::NotEnoughData)?;
+                    offset += self.read_data_into(storage, &chunk, &mut buffer[offset..size])?;
+                }
+
+                if offset != size {
+                    return Err(PartitionError::NotEnoughData.into());
+                }
+                NvsValue::Blob(&buffer[..size])
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl NvsPartition {
+    /// Chunk index offsets of alternating multi-page blob versions
+    const BLOB_VERSIONS: [u8; 2] = [0, 128];
+
+    /// Maximum size of data which can be stored in single page
+    const CHUNK_MAX_SIZE: usize = (Self::ENTRY_COUNT - 1) * NvsItem::SIZE;
+
+    /// Maximum number of namespaces
+    const MAX_NAMESPACES: u8 = 254;
+
+    /// Set value by namespace and key
+    ///
+    /// The namespace will be created when missing.
+    /// The previous value of same type is erased after new one is written.
+    pub fn set_nor_flash<S>(
+        &self,
+        storage: &mut S,
+        namespace: &str,
+        key: &str,
+        value: NvsValue<'_>,
+    ) -> Result<(), NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        // Check arguments before modifying anything
+        NvsItem::new(0, NvsType::U8, namespace)?;
+        NvsItem::new(0, NvsType::U8, key)?;
+        self.prepare_nor_flash(storage)?;
+
+        let ns = match self.find_namespace_nor_flash(storage, namespace)? {
+            Some(ns) => ns,
+            None => self.create_namespace(storage, namespace)?,
+        };
+
+        let type_ = value.type_();
+        let mut item = NvsItem::new(ns, type_, key)?;
+
+        match value {
+            NvsValue::Str(value) => {
+                if value.len() >= Self::CHUNK_MAX_SIZE {
+                    return Err(PartitionError::TooManyData.into());
+                }
+                self.write_item(storage, item, &[value.as_bytes(), b"\0"])?;
+            }
+            NvsValue::Blob(value) => return self.write_blob(storage, ns, key, value),
+            _ => {
+                item.data = value.to_item_data().unwrap();
+                let old = self.find_nor_flash(storage, |old| {
+                    old.ns == ns && old.type_ == type_ && old.key == item.key
+                })?;
+                if old.is_some_and(|old| old.item.data == item.data) {
+                    // Value is not changed
+                    return Ok(());
+                }
+                self.write_item(storage, item, &[])?;
+            }
+        }
+
+        let latest = self.find_nor_flash(storage, |old| {
+            old.ns == ns && old.type_ == type_ && old.key == item.key
+        })?;
+        self.erase_items(
+            storage,
+            |old| old.ns == ns && old.type_ == type_ && old.key == item.key,
+            latest.as_ref(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Erase value by namespace and key
+    ///
+    /// Returns `false` when nothing was erased.
+    pub fn erase_nor_flash<S>(
+        &self,
+        storage: &mut S,
+        namespace: &str,
+        key: &str,
+    ) -> Result<bool, NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        let key = NvsItem::new(0, NvsType::U8, key)?.key;
+        self.prepare_nor_flash(storage)?;
+
+        let Some(ns) = self.find_namespace_nor_flash(storage, namespace)? else {
+            return Ok(false);
+        };
+
+        self.erase_items(storage, |item| item.ns == ns && item.key == key, None)
+    }
+
+    /// Erase all pages of partition
+    pub fn format_nor_flash<S>(&self, storage: &mut S) -> Result<(), NorFlashOpError<S>>
+    where
+        S: NorFlash,
+    {
+        self.check_nor_flash(storage)?;
+        storage
+            .erase(self.offset, self.page_addr(self.page_count()))
+            .map_err(NorFlashOpError::StorageError)
+    }
+
+    fn check_nor_flash<S>(&self, _storage: &S) -> Result<(), NorFlashOpError<S>>
+    where
+        S: NorFlash,
+    {
+        if (self.offset as usize) % Self::PAGE_SIZE != 0
+            || Self::PAGE_SIZE % S::ERASE_SIZE != 0
+            || NvsItem::SIZE % S::WRITE_SIZE != 0
+        {
+            return Err(PartitionError::InvalidAlignment.into());
+        }
+        if self.page_count() < 2 {
+            return Err(PartitionError::InvalidPartition.into());
+        }
+        Ok(())
+    }
+
+    /// Check storage and finish interrupted page reclaim
+    fn prepare_nor_flash<S>(&self, storage: &mut S) -> Result<(), NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        self.check_nor_flash(storage)?;
+
+        let Some((freeing, _)) = self.find_page(storage, NvsPageState::Freeing)? else {
+            // Reclaim is impossible without free page, so blank active page which took
+            // the last one is released
+            if self.free_pages(storage)?.0 == 0 {
+                if let Some((active, _)) = self.find_page(storage, NvsPageState::Active)? {
+                    let states = self.read_states(storage, active)?;
+                    if Self::next_free_entry(&states) == 0
+                        && self.is_entry_blank(storage, active, 0)?
+                    {
+                        self.erase_page(storage, active)?;
+                    }
+                }
+            }
+            return Ok(());
+        };
+
+        // The page activated for copying items may be incomplete
+        if let Some((active, _)) = self.find_page(storage, NvsPageState::Active)? {
+            self.erase_page(storage, active)?;
+        }
+
+        let page = self.activate_page(storage)?;
+        self.copy_items(storage, freeing, page)?;
+        self.erase_page(storage, freeing)
+    }
+
+    /// Find page with specified state and highest sequence number
+    fn find_page<S>(
+        &self,
+        storage: &mut S,
+        state: NvsPageState,
+    ) -> Result<Option<(usize, u32)>, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        let mut found = None;
+        for page in 0..self.page_count() {
+            let header = self.read_header(storage, page)?;
+            if header.is_valid()
+                && header.state == state
+                && found.map_or(true, |(_, seq)| header.seq > seq)
+            {
+                found = Some((page, header.seq));
+            }
+        }
+        Ok(found)
+    }
+
+    /// Get number of free pages and index of first one
+    fn free_pages<S>(&self, storage: &mut S) -> Result<(usize, Option<usize>), NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        let mut count = 0;
+        let mut first = None;
+        for page in 0..self.page_count() {
+            if !self.read_header(storage, page)?.is_valid() {
+                count += 1;
+                first = first.or(Some(page));
+            }
+        }
+        Ok((count, first))
+    }
+
+    /// Get index of first free entry in page
+    fn next_free_entry(states: &NvsEntryStates) -> usize {
+        (0..Self::ENTRY_COUNT)
+            .rposition(|index| states.get(index) != NvsEntryState::Empty)
+            .map_or(0, |index| index + 1)
+    }
+
+    /// Get active page and index of first free entry
+    ///
+    /// Entries which were programmed but not marked as written due to power loss are
+    /// marked as erased and skipped like `Page::load` from ESP-IDF does.
+    fn current_page<S>(&self, storage: &mut S) -> Result<(usize, usize), NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        if let Some((page, _)) = self.find_page(storage, NvsPageState::Active)? {
+            let mut states = self.read_states(storage, page)?;
+            let mut next = Self::next_free_entry(&states);
+            while next < Self::ENTRY_COUNT && !self.is_entry_blank(storage, page, next)? {
+                states.set(next, NvsEntryState::Erased);
+                self.write_states(storage, page, &states, next..next + 1)?;
+                next += 1;
+            }
+            return Ok((page, next));
+        }
+        self.request_new_page(storage)
+    }
+
+    /// Check that entry is not programmed
+    fn is_entry_blank<S>(
+        &self,
+        storage: &mut S,
+        page: usize,
+        index: usize,
+    ) -> Result<bool, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        let mut data = [0; NvsItem::SIZE];
+        storage
+            .read(self.entry_addr(page, index), &mut data)
+            .map_err(NorFlashOpError::StorageError)?;
+        Ok(data.iter().all(|byte| *byte == 0xff))
+    }
+
+    /// Mark active page as full and activate next one
+    ///
+    /// When the only free page left the page with most unused entries is reclaimed.
+    fn request_new_page<S>(&self, storage: &mut S) -> Result<(usize, usize), NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        if let Some((page, _)) = self.find_page(storage, NvsPageState::Active)? {
+            self.set_page_state(storage, page, NvsPageState::Full)?;
+        }
+
+        let (free, _) = self.free_pages(storage)?;
+        if free == 0 {
+            return Err(PartitionError::NotEnoughSpace.into());
+        }
+        if free >= 2 {
+            return Ok((self.activate_page(storage)?, 0));
+        }
+
+        let mut reclaim = None;
+        let mut max_unused = 0;
+        for page in 0..self.page_count() {
+            if !self.read_header(storage, page)?.is_valid() {
+                continue;
+            }
+            let states = self.read_states(storage, page)?;
+            let unused = (0..Self::ENTRY_COUNT)
+                .filter(|index| states.get(*index) != NvsEntryState::Written)
+                .count();
+            if unused > max_unused {
+                reclaim = Some(page);
+                max_unused = unused;
+            }
+        }
+        let reclaim = reclaim.ok_or(PartitionError::NotEnoughSpace)?;
+
+        // Reclaimed page is marked first to make interrupted reclaim recoverable
+        self.set_page_state(storage, reclaim, NvsPageState::Freeing)?;
+        let page = self.activate_page(storage)?;
+        let next = self.copy_items(storage, reclaim, page)?;
+        self.erase_page(storage, reclaim)?;
+
+        Ok((page, next))
+    }
+
+    /// Initialize first free page as active
+    fn activate_page<S>(&self, storage: &mut S) -> Result<usize, NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        let mut seq = None;
+        for page in 0..self.page_count() {
+            let header = self.read_header(storage, page)?;
+            if header.is_valid() && seq.map_or(true, |seq| header.seq > seq) {
+                seq = Some(header.seq);
+            }
+        }
+        let seq = seq.map_or(0, |seq| seq + 1);
+
+        let (_, page) = self.free_pages(storage)?;
+        let page = page.ok_or(PartitionError::NotEnoughSpace)?;
+
+        self.erase_page(storage, page)?;
+        let header = NvsPageHeader::new(NvsPageState::Active, seq, NvsPageHeader::VERSION2);
+        let mut data = [0; NvsPageHeader::SIZE];
+        header.to_bytes(&mut data);
+        storage
+            .write(self.page_addr(page), &data)
+            .map_err(NorFlashOpError::StorageError)?;
+
+        Ok(page)
+    }
+
+    fn erase_page<S>(&self, storage: &mut S, page: usize) -> Result<(), NorFlashOpError<S>>
+    where
+        S: NorFlash,
+    {
+        storage
+            .erase(self.page_addr(page), self.page_addr(page + 1))
+            .map_err(NorFlashOpError::StorageError)
+    }
+
+    fn set_page_state<S>(
+        &self,
+        storage: &mut S,
+        page: usize,
+        state: NvsPageState,
+    ) -> Result<(), NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        let mut header = self.read_header(storage, page)?;
+        header.state = state;
+        let mut data = [0; NvsPageHeader::SIZE];
+        header.to_bytes(&mut data);
+        storage
+            .write(self.page_addr(page), &data[..S::WRITE_SIZE.max(4)])
+            .map_err(NorFlashOpError::StorageError)
+    }
+
+    /// Write words of state bitmap which contains specified entries
+    fn write_states<S>(
+        &self,
+        storage: &mut S,
+        page: usize,
+        states: &NvsEntryStates,
+        entries: core::ops::Range<usize>,
+    ) -> Result<(), NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        let word = S::WRITE_SIZE.max(4);
+        let start = entries.start / 4 / word * word;
+        let end = (entries.end - 1) / 4 / word * word + word;
+        storage
+            .write(self.states_addr(page) + start as u32, &states.0[start..end])
+            .map_err(NorFlashOpError::StorageError)
+    }
+
+    fn write_entry<S>(
+        &self,
+        storage: &mut S,
+        page: usize,
+        index: usize,
+        data: &[u8; NvsItem::SIZE],
+    ) -> Result<(), NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        let addr = self.entry_addr(page, index);
+        #[cfg(feature = "aes")]
+        let data = &self.cipher().map_or(*data, |cipher| {
+            let mut data = *data;
+            cipher.encrypt(addr - self.offset, &mut data);
+            data
+        });
+        storage
+            .write(addr, data)
+            .map_err(NorFlashOpError::StorageError)
+    }
+
+    /// Copy written items between pages
+    ///
+    /// Returns index of first free entry in target page.
+    fn copy_items<S>(
+        &self,
+        storage: &mut S,
+        from: usize,
+        to: usize,
+    ) -> Result<usize, NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        let states = self.read_states(storage, from)?;
+        let mut target_states = self.read_states(storage, to)?;
+        let mut next = Self::next_free_entry(&target_states);
+        let mut index = 0;
+
+        while let Some((found, item)) = self.next_item(storage, from, &states, index)? {
+            let span = item.span as usize;
+            if next + span > Self::ENTRY_COUNT {
+                return Err(PartitionError::NotEnoughSpace.into());
+            }
+            for offset in 0..span {
+                let data = self.read_entry(storage, from, found + offset)?;
+                self.write_entry(storage, to, next + offset, &data)?;
+                target_states.set(next + offset, NvsEntryState::Written);
+                self.write_states(
+                    storage,
+                    to,
+                    &target_states,
+                    next + offset..next + offset + 1,
+                )?;
+            }
+            next += span;
+            index = found + span;
+        }
+
+        Ok(next)
+    }
+
+    /// Write item followed by data entries into active page
+    ///
+    /// The data is concatenation of specified parts.
+    fn write_item<S>(
+        &self,
+        storage: &mut S,
+        mut item: NvsItem,
+        data: &[&[u8]],
+    ) -> Result<(), NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        item.seal(data);
+        let span = item.span as usize;
+
+        let (mut page, mut index) = self.current_page(storage)?;
+        if index + span > Self::ENTRY_COUNT {
+            (page, index) = self.request_new_page(storage)?;
+            if index + span > Self::ENTRY_COUNT {
+                return Err(PartitionError::NotEnoughSpace.into());
+            }
+        }
+
+        let mut states = self.read_states(storage, page)?;
+        for (index, entry) in (index..).zip(item.entries(data)) {
+            self.write_entry(storage, page, index, &entry)?;
+            states.set(index, NvsEntryState::Written);
+            self.write_states(storage, page, &states, index..index + 1)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write binary data as multi-page blob
+    fn write_blob<S>(
+        &self,
+        storage: &mut S,
+        ns: u8,
+        key: &str,
+        data: &[u8],
+    ) -> Result<(), NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        if data.len() > (self.page_count() - 1) * Self::CHUNK_MAX_SIZE {
+            return Err(PartitionError::TooManyData.into());
+        }
+        let item = NvsItem::new(ns, NvsType::BlobIndex, key)?;
+
+        let old = self.find_nor_flash(storage, |old| {
+            old.ns == ns && old.type_ == NvsType::BlobIndex && old.key == item.key
+        })?;
+        let old_start = old.map(|old| old.item.blob_index().2);
+        let chunk_start = if old_start == Some(Self::BLOB_VERSIONS[0]) {
+            Self::BLOB_VERSIONS[1]
+        } else {
+            Self::BLOB_VERSIONS[0]
+        };
+
+        let mut offset = 0;
+        let mut chunk_count = 0u8;
+        let mut retries = 0;
+        loop {
+            let (_, next) = self.current_page(storage)?;
+            let tailroom = (Self::ENTRY_COUNT - 1).saturating_sub(next) * NvsItem::SIZE;
+            let remaining = data.len() - offset;
+
+            if chunk_count == 0
+                && (tailroom < remaining || tailroom == 0 && remaining == 0)
+                && tailroom < Self::CHUNK_MAX_SIZE / 10
+            {
+                // Do not split beginning of blob into tiny chunk
+                retries += 1;
+                if retries > self.page_count() {
+                    return Err(PartitionError::NotEnoughSpace.into());
+                }
+                self.request_new_page(storage)?;
+                continue;
+            } else if tailroom == 0 {
+                return Err(PartitionError::NotEnoughSpace.into());
+            }
+            if chunk_count >= Self::BLOB_VERSIONS[1] - 1 {
+                return Err(PartitionError::TooManyData.into());
+            }
+
+            let size = remaining.min(tailroom);
+            let chunk = NvsItem {
+                type_: NvsType::BlobData,
+                chunk_index: chunk_start + chunk_count,
+                ..item
+            };
+            self.write_item(storage, chunk, &[&data[offset..offset + size]])?;
+            chunk_count += 1;
+            offset += size;
+
+            if offset < data.len() || tailroom - size < NvsItem::SIZE {
+                self.request_new_page(storage)?;
+            }
+            if offset == data.len() {
+                break;
+            }
+        }
+
+        let mut index = item;
+        index.data[..4].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        index.data[4] = chunk_count;
+        index.data[5] = chunk_start;
+        self.write_item(storage, index, &[])?;
+
+        // Erase previous version of blob
+        self.erase_items(
+            storage,
+            |old| {
+                old.ns == ns
+                    && old.key == item.key
+                    && match old.type_ {
+                        NvsType::Blob => true,
+                        NvsType::BlobIndex => old.blob_index().2 != chunk_start,
+                        NvsType::BlobData => {
+                            old.chunk_index.wrapping_sub(chunk_start) >= Self::BLOB_VERSIONS[1]
+                        }
+                        _ => false,
+                    }
+            },
+            None,
+        )?;
+
+        Ok(())
+    }
+
+    /// Create namespace with first unused index
+    fn create_namespace<S>(&self, storage: &mut S, name: &str) -> Result<u8, NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        let mut used = [false; 256];
+        for entry in self.iter_nor_flash(storage) {
+            let entry = entry?;
+            if entry.item.is_namespace() {
+                used[entry.item.data[0] as usize] = true;
+            }
+        }
+
+        let ns = (1..=Self::MAX_NAMESPACES)
+            .find(|ns| !used[*ns as usize])
+            .ok_or(PartitionError::NotEnoughSpace)?;
+
+        let mut item = NvsItem::new(0, NvsType::U8, name)?;
+        item.data[0] = ns;
+        self.write_item(storage, item, &[])?;
+
+        Ok(ns)
+    }
+
+    /// Erase items which satisfy predicate except specified one
+    ///
+    /// Returns `true` when at least one item was erased.
+    fn erase_items<S>(
+        &self,
+        storage: &mut S,
+        mut predicate: impl FnMut(&NvsItem) -> bool,
+        keep: Option<&NvsEntry>,
+    ) -> Result<bool, NorFlashOpError<S>>
+    where
+        S: MultiwriteNorFlash,
+    {
+        let mut erased = false;
+
+        for page in 0..self.page_count() {
+            let header = self.read_header(storage, page)?;
+            if !header.is_valid() {
+                continue;
+            }
+            let mut states = self.read_states(storage, page)?;
+            let mut index = 0;
+
+            while let Some((found, item)) = self.next_item(storage, page, &states, index)? {
+                let end = found + item.span as usize;
+                index = end;
+
+                if !predicate(&item)
+                    || keep.is_some_and(|keep| (keep.page, keep.index) == (page, found))
+                {
+                    continue;
+                }
+                for index in found..end {
+                    states.set(index, NvsEntryState::Erased);
+                }
+                self.write_states(storage, page, &states, found..end)?;
+                erased = true;
+            }
+        }
+
+        Ok(erased)
+    }
+}
+
+/// Iterator over written items in NVS partition
+#[cfg(feature = "embedded-storage")]
+pub struct NvsNorFlashIter<'s, S> {
+    storage: &'s mut S,
+    partition: NvsPartition,
+    page: usize,
+    index: usize,
+    seq: u32,
+    states: NvsEntryStates,
+}
+
+#[cfg(feature = "embedded-storage")]
+impl<S> NvsNorFlashIter<'_, S>
+where
+    S: ReadNorFlash,
+{
+    /// Read next entry
+    ///
+    /// Returns `None` when end of partition reached.
+    pub fn next_entry(&mut self) -> Result<Option<NvsEntry>, NorFlashOpError<S>> {
+        loop {
+            if self.index >= NvsPartition::ENTRY_COUNT {
+                if self.page >= self.partition.page_count() {
+                    return Ok(None);
+                }
+                let page = self.page;
+                self.page += 1;
+
+                let header = self.partition.read_header(self.storage, page)?;
+                if !header.is_valid() {
+                    continue;
+                }
+                self.states = self.partition.read_states(self.storage, page)?;
+                self.seq = header.seq;
+                self.index = 0;
+            }
+
+            let page = self.page - 1;
+            let Some((index, item)) =
+                self.partition
+                    .next_item(self.storage, page, &self.states, self.index)?
+            else {
+                self.index = NvsPartition::ENTRY_COUNT;
+                continue;
+            };
+            self.index = index + item.span as usize;
+
+            return Ok(Some(NvsEntry {
+                page,
+                seq: self.seq,
+                index,
+                item,
+            }));
+        }
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl<S> Iterator for NvsNorFlashIter<'_, S>
+where
+    S: ReadNorFlash,
+{
+    type Item = Result<NvsEntry, NorFlashOpError<S>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.next_entry().transpose();
+        if matches!(result, Some(Err(_))) {
+            // Stop iteration on storage errors
+            self.page = self.partition.page_count();
+            self.index = NvsPartition::ENTRY_COUNT;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    extern crate std;
+    #[cfg(feature = "embedded-storage")]
+    use std::{vec, vec::Vec};
+
+    const NVS: &[u8; 0x5000] = include_bytes!("../tests/nvs.bin");
+
+    #[test]
+    fn page_header() {
+        let header = NvsPageHeader::from_bytes(NVS.first_chunk().unwrap());
+        assert_eq!(header.state, NvsPageState::Full);
+        assert_eq!(header.seq, 0);
+        assert_eq!(header.version, NvsPageHeader::VERSION2);
+        assert!(header.is_valid());
+        assert_eq!(
+            header,
+            NvsPageHeader::new(NvsPageState::Full, 0, NvsPageHeader::VERSION2)
+        );
+
+        let header =
+            NvsPageHeader::from_bytes(NVS[4 * NvsPartition::PAGE_SIZE..].first_chunk().unwrap());
+        assert_eq!(header, NvsPageHeader::default());
+        assert!(!header.is_valid());
+
+        let states = NvsEntryStates(*NVS[32..].first_chunk().unwrap());
+        assert_eq!(states.get(0), NvsEntryState::Written);
+        assert_eq!(states.get(125), NvsEntryState::Written);
+
+        let mut states = NvsEntryStates::default();
+        assert_eq!(states.get(5), NvsEntryState::Empty);
+        states.set(5, NvsEntryState::Written);
+        assert_eq!(states.get(5), NvsEntryState::Written);
+        assert_eq!(states.0[1], 0b1111_1011);
+        states.set(5, NvsEntryState::Erased);
+        assert_eq!(states.get(5), NvsEntryState::Erased);
+        assert_eq!(states.get(4), NvsEntryState::Empty);
+    }
+
+    #[test]
+    fn items() {
+        let item = NvsItem::from_bytes(NVS[64..].first_chunk().unwrap());
+        assert!(item.is_namespace());
+        assert!(item.is_valid());
+        assert_eq!(item.key(), "storage");
+        assert_eq!(item.value(), Some(NvsValue::U8(1)));
+
+        let item = NvsItem::from_bytes(NVS[64 + 32 * 8..].first_chunk().unwrap());
+        assert_eq!(item.type_, NvsType::I64);
+        assert_eq!(item.key(), "i64_key");
+        assert_eq!(item.value(), Some(NvsValue::I64(i64::MIN)));
+
+        let item = NvsItem::from_bytes(NVS[64 + 32 * 9..].first_chunk().unwrap());
+        assert_eq!(item.type_, NvsType::Str);
+        assert_eq!(item.span, 2);
+        assert_eq!(item.data_size(), 10);
+        assert_eq!(item.value(), None);
+
+        let mut data = [0; NvsItem::SIZE];
+        item.to_bytes(&mut data);
+        assert_eq!(&data, NVS[64 + 32 * 9..].first_chunk().unwrap());
+
+        let item = NvsItem { span: 3, ..item };
+        assert!(!item.is_valid());
+    }
+
+    #[cfg(feature = "embedded-storage")]
+    type Flash = NorFlashSim<Vec<u8>, 4, 4, { NvsPartition::PAGE_SIZE }>;
+
+    /// Flash with partition data at 0x9000
+    #[cfg(feature = "embedded-storage")]
+    fn flash(data: &[u8]) -> Flash {
+        let mut flash = vec![0xff; 0x9000];
+        flash.extend_from_slice(data);
+        NorFlashSim::new(flash)
+    }
+
+    #[cfg(feature = "embedded-storage")]
+    #[test]
+    fn read_nor_flash() {
+        let partition =
+            PartitionEntry::new(DataPartitionType::Nvs, 0x9000, NVS.len(), "nvs", false).unwrap();
+        let nvs = NvsPartition::try_from(&partition).unwrap();
+        let mut flash = flash(NVS);
+
+        assert_eq!(nvs.iter_nor_flash(&mut flash).count(), 21);
+        assert_eq!(
+            nvs.find_namespace_nor_flash(&mut flash, "storage"),
+            Ok(Some(1))
+        );
+        assert_eq!(
+            nvs.find_namespace_nor_flash(&mut flash, "calibration"),
+            Ok(Some(2))
+        );
+        assert_eq!(
+            nvs.find_namespace_nor_flash(&mut flash, "unknown"),
+            Ok(None)
+        );
+
+        let mut buffer = [0; 4000];
+        let mut get = |namespace, key| {
+            nvs.get_nor_flash(&mut flash, namespace, key, &mut buffer)
+                .unwrap()
+                .map(|value| match value {
+                    NvsValue::Str(value) => value.len() as i128,
+                    NvsValue::Blob(value) => -(value.len() as i128),
+                    NvsValue::U8(value) => value as i128,
+                    NvsValue::I8(value) => value as i128,
+                    NvsValue::U16(value) => value as i128,
+                    NvsValue::I16(value) => value as i128,
+                    NvsValue::U32(value) => value as i128,
+                    NvsValue::I32(value) => value as i128,
+                    NvsValue::U64(value) => value as i128,
+                    NvsValue::I64(value) => value as i128,
+                })
+        };
+
+        assert_eq!(get("storage", "u8_key"), Some(255));
+        assert_eq!(get("storage", "i8_key"), Some(-128));
+        assert_eq!(get("storage", "u16_key"), Some(65535));
+        assert_eq!(get("storage", "i16_key"), Some(-32768));
+        assert_eq!(get("storage", "u32_key"), Some(u32::MAX as i128));
+        assert_eq!(get("storage", "i32_key"), Some(i32::MIN as i128));
+        assert_eq!(get("storage", "u64_key"), Some(u64::MAX as i128));
+        assert_eq!(get("storage", "i64_key"), Some(i64::MIN as i128));
+        assert_eq!(get("calibration", "u8_key"), Some(1));
+        assert_eq!(get("calibration", "long_str"), Some(1439));
+        assert_eq!(get("calibration", "big_blob"), Some(-3000));
+        assert_eq!(get("calibration", "i8_key"), None);
+        assert_eq!(get("unknown", "u8_key"), None);
+
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "str_key", &mut buffer),
+            Ok(Some(NvsValue::Str("Hello NVS")))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "hex_key", &mut buffer),
+            Ok(Some(NvsValue::Blob(&[
+                0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef
+            ])))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "b64_key", &mut buffer),
+            Ok(Some(NvsValue::Blob(b"Hello base64")))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "calibration", "serial", &mut buffer),
+            Ok(Some(NvsValue::Str("SN-0001")))
+        );
+
+        let Ok(Some(NvsValue::Blob(blob))) =
+            nvs.get_nor_flash(&mut flash, "calibration", "big_blob", &mut buffer)
+        else {
+            panic!()
+        };
+        assert!(blob
+            .iter()
+            .enumerate()
+            .all(|(index, byte)| *byte == (index * 37 + 11) as u8));
+
+        let mut buffer = [0; 4];
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "calibration", "serial", &mut buffer),
+            Err(NorFlashOpError::PartitionError(PartitionError::TooManyData))
+        );
+    }
+
+    #[cfg(feature = "embedded-storage")]
+    #[test]
+    fn write_nor_flash() {
+        let nvs = NvsPartition::new(0x9000, 0x4000);
+        let mut flash = flash(&[0; 0x4000]);
+        nvs.format_nor_flash(&mut flash).unwrap();
+
+        let mut blob = [0; 5000];
+        for (index, byte) in blob.iter_mut().enumerate() {
+            *byte = (index * 13 + 7) as u8;
+        }
+
+        nvs.set_nor_flash(&mut flash, "storage", "u8_key", NvsValue::U8(42))
+            .unwrap();
+        nvs.set_nor_flash(&mut flash, "storage", "i64_key", NvsValue::I64(-1))
+            .unwrap();
+        nvs.set_nor_flash(&mut flash, "storage", "str_key", NvsValue::Str("Hello"))
+            .unwrap();
+        nvs.set_nor_flash(&mut flash, "other", "blob_key", NvsValue::Blob(&blob))
+            .unwrap();
+
+        let header = nvs.read_header(&mut flash, 0).unwrap();
+        assert_eq!(header.state, NvsPageState::Full);
+        assert_eq!(header.seq, 0);
+        let header = nvs.read_header(&mut flash, 1).unwrap();
+        assert_eq!(header.state, NvsPageState::Active);
+        assert_eq!(header.seq, 1);
+        let header = nvs.read_header(&mut flash, 2).unwrap();
+        assert_eq!(header.state, NvsPageState::Uninitialized);
+
+        let mut buffer = [0; 5000];
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "u8_key", &mut buffer),
+            Ok(Some(NvsValue::U8(42)))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "i64_key", &mut buffer),
+            Ok(Some(NvsValue::I64(-1)))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "str_key", &mut buffer),
+            Ok(Some(NvsValue::Str("Hello")))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "other", "blob_key", &mut buffer),
+            Ok(Some(NvsValue::Blob(&blob)))
+        );
+        assert_eq!(
+            nvs.find_namespace_nor_flash(&mut flash, "other"),
+            Ok(Some(2))
+        );
+
+        nvs.set_nor_flash(&mut flash, "storage", "u8_key", NvsValue::U8(43))
+            .unwrap();
+        nvs.set_nor_flash(&mut flash, "storage", "str_key", NvsValue::Str("World"))
+            .unwrap();
+        nvs.set_nor_flash(
+            &mut flash,
+            "other",
+            "blob_key",
+            NvsValue::Blob(&blob[..100]),
+        )
+        .unwrap();
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "u8_key", &mut buffer),
+            Ok(Some(NvsValue::U8(43)))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "str_key", &mut buffer),
+            Ok(Some(NvsValue::Str("World")))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "other", "blob_key", &mut buffer),
+            Ok(Some(NvsValue::Blob(&blob[..100])))
+        );
+        // namespaces, u8, i64, str, blob data, blob index
+        assert_eq!(nvs.iter_nor_flash(&mut flash).count(), 7);
+
+        assert_eq!(
+            nvs.erase_nor_flash(&mut flash, "storage", "str_key"),
+            Ok(true)
+        );
+        assert_eq!(
+            nvs.erase_nor_flash(&mut flash, "storage", "str_key"),
+            Ok(false)
+        );
+        assert_eq!(
+            nvs.erase_nor_flash(&mut flash, "other", "blob_key"),
+            Ok(true)
+        );
+        assert_eq!(
+            nvs.erase_nor_flash(&mut flash, "unknown", "blob_key"),
+            Ok(false)
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "str_key", &mut buffer),
+            Ok(None)
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "other", "blob_key", &mut buffer),
+            Ok(None)
+        );
+        assert_eq!(nvs.iter_nor_flash(&mut flash).count(), 4);
+
+        assert_eq!(
+            nvs.set_nor_flash(&mut flash, "storage", "too_long_key_name", NvsValue::U8(0)),
+            Err(NorFlashOpError::PartitionError(
+                PartitionError::InvalidString
+            ))
+        );
+        assert_eq!(
+            nvs.set_nor_flash(
+                &mut flash,
+                "storage",
+                "blob_key",
+                NvsValue::Blob(&[0; 12001])
+            ),
+            Err(NorFlashOpError::PartitionError(PartitionError::TooManyData))
+        );
+    }
+
+    #[cfg(feature = "embedded-storage")]
+    #[test]
+    fn reclaim_nor_flash() {
+        let nvs = NvsPartition::new(0x9000, 0x3000);
+        let mut flash = flash(&[0; 0x3000]);
+        nvs.format_nor_flash(&mut flash).unwrap();
+
+        let mut blob = [0; 2000];
+        let mut buffer = [0; 2000];
+
+        for value in 0..500 {
+            nvs.set_nor_flash(&mut flash, "storage", "counter", NvsValue::U32(value))
+                .unwrap();
+            if value % 25 == 0 {
+                blob.fill(value as u8);
+                nvs.set_nor_flash(&mut flash, "storage", "blob", NvsValue::Blob(&blob))
+                    .unwrap();
+            }
+        }
+
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "counter", &mut buffer),
+            Ok(Some(NvsValue::U32(499)))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "blob", &mut buffer),
+            Ok(Some(NvsValue::Blob(&[(475 % 256) as u8; 2000])))
+        );
+        assert_eq!(nvs.free_pages(&mut flash).unwrap().0, 1);
+    }
+
+    #[cfg(feature = "embedded-storage")]
+    #[test]
+    fn recover_nor_flash() {
+        let nvs = NvsPartition::new(0x9000, 0x3000);
+        let mut flash = flash(&[0; 0x3000]);
+        nvs.format_nor_flash(&mut flash).unwrap();
+        let mut buffer = [0; 16];
+
+        nvs.set_nor_flash(&mut flash, "storage", "first", NvsValue::U16(1))
+            .unwrap();
+        nvs.set_nor_flash(&mut flash, "storage", "second", NvsValue::Str("2"))
+            .unwrap();
+
+        // Power lost after activating page for copying items
+        nvs.set_page_state(&mut flash, 0, NvsPageState::Full)
+            .unwrap();
+        nvs.set_page_state(&mut flash, 0, NvsPageState::Freeing)
+            .unwrap();
+        let page = nvs.activate_page(&mut flash).unwrap();
+        assert_eq!(page, 1);
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "second", &mut buffer),
+            Ok(Some(NvsValue::Str("2")))
+        );
+
+        nvs.set_nor_flash(&mut flash, "storage", "third", NvsValue::I32(-3))
+            .unwrap();
+        assert_eq!(nvs.find_page(&mut flash, NvsPageState::Freeing), Ok(None));
+        assert_eq!(
+            nvs.find_page(&mut flash, NvsPageState::Active),
+            Ok(Some((1, 1)))
+        );
+        assert!(!nvs.read_header(&mut flash, 0).unwrap().is_valid());
+
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "first", &mut buffer),
+            Ok(Some(NvsValue::U16(1)))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "second", &mut buffer),
+            Ok(Some(NvsValue::Str("2")))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "third", &mut buffer),
+            Ok(Some(NvsValue::I32(-3)))
+        );
+    }
+
+    #[cfg(feature = "embedded-storage")]
+    #[test]
+    fn recover_no_free_pages_nor_flash() {
+        let nvs = NvsPartition::new(0x9000, 0x2000);
+        let mut flash = flash(&[0; 0x2000]);
+        nvs.format_nor_flash(&mut flash).unwrap();
+        let mut buffer = [0; 16];
+
+        nvs.set_nor_flash(&mut flash, "storage", "value", NvsValue::U16(1))
+            .unwrap();
+
+        // Power lost after activating the last free page but before marking page as freeing
+        nvs.set_page_state(&mut flash, 0, NvsPageState::Full)
+            .unwrap();
+        assert_eq!(nvs.activate_page(&mut flash), Ok(1));
+        assert_eq!(nvs.free_pages(&mut flash), Ok((0, None)));
+
+        nvs.set_nor_flash(&mut flash, "storage", "value", NvsValue::U16(2))
+            .unwrap();
+        assert_eq!(nvs.free_pages(&mut flash).unwrap().0, 1);
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "value", &mut buffer),
+            Ok(Some(NvsValue::U16(2)))
+        );
+    }
+
+    #[cfg(feature = "embedded-storage")]
+    #[test]
+    fn power_cut_nor_flash() {
+        let nvs = NvsPartition::new(0, 0x3000);
+        let mut flash = NorFlashSim::<_>::new([0xff; 0x3000]);
+        nvs.format_nor_flash(&mut flash).unwrap();
+        let mut buffer = [0; 16];
+
+        nvs.set_nor_flash(&mut flash, "storage", "value", NvsValue::U32(1))
+            .unwrap();
+        let (page, next) = nvs.current_page(&mut flash).unwrap();
+
+        // Power lost after programming entry but before updating its state
+        flash.power_cut_after(NvsItem::SIZE);
+        assert!(nvs
+            .set_nor_flash(&mut flash, "storage", "value", NvsValue::U32(2))
+            .is_err());
+        flash.power_on();
+        assert!(!nvs.is_entry_blank(&mut flash, page, next).unwrap());
+        assert_eq!(
+            nvs.read_states(&mut flash, page).unwrap().get(next),
+            NvsEntryState::Empty
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "value", &mut buffer),
+            Ok(Some(NvsValue::U32(1)))
+        );
+
+        nvs.set_nor_flash(&mut flash, "storage", "value", NvsValue::U32(3))
+            .unwrap();
+        assert_eq!(
+            nvs.read_states(&mut flash, page).unwrap().get(next),
+            NvsEntryState::Erased
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "value", &mut buffer),
+            Ok(Some(NvsValue::U32(3)))
+        );
+        // namespace and value
+        assert_eq!(nvs.iter_nor_flash(&mut flash).count(), 2);
+    }
+
+    #[cfg(feature = "embedded-storage")]
+    #[test]
+    fn power_cut_reclaim_nor_flash() {
+        let nvs = NvsPartition::new(0, 0x3000);
+        let mut flash = NorFlashSim::<_>::new([0xff; 0x3000]);
+        nvs.format_nor_flash(&mut flash).unwrap();
+        let mut buffer = [0; 16];
+
+        nvs.set_nor_flash(&mut flash, "storage", "const", NvsValue::U32(0xc0de))
+            .unwrap();
+
+        // Fill pages until the next write requires reclaim
+        let mut value = 0;
+        while nvs.free_pages(&mut flash).unwrap().0 > 1
+            || nvs.current_page(&mut flash).unwrap().1 < NvsPartition::ENTRY_COUNT
+        {
+            value += 1;
+            nvs.set_nor_flash(&mut flash, "storage", "counter", NvsValue::U32(value))
+                .unwrap();
+        }
+
+        let start = flash.written();
+        let mut reference = flash.clone();
+        nvs.set_nor_flash(
+            &mut reference,
+            "storage",
+            "counter",
+            NvsValue::U32(value + 1),
+        )
+        .unwrap();
+        let total = reference.written() - start;
+
+        for cut in 0..total {
+            let mut flash = flash.clone();
+            flash.power_cut_after(cut);
+            let written = nvs
+                .set_nor_flash(&mut flash, "storage", "counter", NvsValue::U32(value + 1))
+                .is_ok();
+            assert!(!written);
+            flash.power_on();
+
+            let current = nvs
+                .get_nor_flash(&mut flash, "storage", "counter", &mut buffer)
+                .unwrap();
+            assert!(
+                current == Some(NvsValue::U32(value)) || current == Some(NvsValue::U32(value + 1)),
+                "cut after {cut} bytes"
+            );
+
+            nvs.set_nor_flash(&mut flash, "storage", "counter", NvsValue::U32(value + 2))
+                .unwrap_or_else(|error| panic!("cut after {cut} bytes: {error:?}"));
+            assert_eq!(
+                nvs.get_nor_flash(&mut flash, "storage", "counter", &mut buffer),
+                Ok(Some(NvsValue::U32(value + 2)))
+            );
+            assert_eq!(
+                nvs.get_nor_flash(&mut flash, "storage", "const", &mut buffer),
+                Ok(Some(NvsValue::U32(0xc0de)))
+            );
+            assert_eq!(
+                nvs.free_pages(&mut flash).unwrap().0,
+                1,
+                "cut after {cut} bytes"
+            );
+        }
+    }
+
+    #[cfg(feature = "embedded-storage")]
+    #[test]
+    fn write_like_generator() {
+        // Rewriting values in same order should give same layout
+        let nvs = NvsPartition::new(0x9000, NVS.len());
+        let mut source = flash(NVS);
+        let mut flash = flash(&[0; 0x5000]);
+        nvs.format_nor_flash(&mut flash).unwrap();
+
+        let keys = [
+            ("storage", "u8_key"),
+            ("storage", "i8_key"),
+            ("storage", "u16_key"),
+            ("storage", "i16_key"),
+            ("storage", "u32_key"),
+            ("storage", "i32_key"),
+            ("storage", "u64_key"),
+            ("storage", "i64_key"),
+            ("storage", "str_key"),
+            ("storage", "hex_key"),
+            ("storage", "b64_key"),
+            ("calibration", "u8_key"),
+            ("calibration", "long_str"),
+            ("calibration", "big_blob"),
+            ("calibration", "serial"),
+        ];
+        let mut buffer = [0; 4000];
+        for (namespace, key) in keys {
+            let value = nvs
+                .get_nor_flash(&mut source, namespace, key, &mut buffer)
+                .unwrap()
+                .unwrap();
+            nvs.set_nor_flash(&mut flash, namespace, key, value)
+                .unwrap();
+        }
+
+        assert_eq!(flash.data()[0x9000..0xa000], NVS[..0x1000]);
+        assert_eq!(flash.data()[0xa020..0xb000], NVS[0x1020..0x2000]);
+
+        // Writing into image with single free page requires reclaim
+        nvs.set_nor_flash(
+            &mut source,
+            "calibration",
+            "serial",
+            NvsValue::Str("SN-0002"),
+        )
+        .unwrap();
+        assert_eq!(
+            nvs.get_nor_flash(&mut source, "calibration", "serial", &mut buffer),
+            Ok(Some(NvsValue::Str("SN-0002")))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut source, "storage", "str_key", &mut buffer),
+            Ok(Some(NvsValue::Str("Hello NVS")))
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut source, "calibration", "big_blob", &mut buffer)
+                .map(|value| value
+                    .map(|value| matches!(value, NvsValue::Blob(data) if data.len() == 3000))),
+            Ok(Some(true))
+        );
+    }
+
+    #[cfg(all(feature = "aes", feature = "embedded-storage"))]
+    #[test]
+    fn encrypted_nor_flash() {
+        const NVS_ENC: &[u8; 0x5000] = include_bytes!("../tests/nvs_enc.bin");
+        let keys = include_bytes!("../tests/nvs_keys.bin");
+        let keys = NvsKeys::from_bytes(keys.first_chunk().unwrap())
+            .unwrap()
+            .unwrap();
+
+        let plain = NvsPartition::new(0x9000, NVS.len());
+        let nvs = plain.with_keys(keys);
+        assert_eq!(nvs.keys(), Some(&keys));
+        let mut source = flash(NVS_ENC);
+        assert_eq!(plain.iter_nor_flash(&mut source).count(), 0);
+        assert_eq!(nvs.iter_nor_flash(&mut source).count(), 21);
+
+        let mut buffer = [0; 4000];
+        assert_eq!(
+            nvs.get_nor_flash(&mut source, "storage", "str_key", &mut buffer),
+            Ok(Some(NvsValue::Str("Hello NVS")))
+        );
+
+        let mut plain_source = flash(NVS);
+        let mut flash = flash(&[0; 0x5000]);
+        nvs.format_nor_flash(&mut flash).unwrap();
+        for key in ["u8_key", "i8_key", "u16_key", "i16_key", "u32_key"] {
+            let value = plain
+                .get_nor_flash(&mut plain_source, "storage", key, &mut buffer)
+                .unwrap()
+                .unwrap();
+            nvs.set_nor_flash(&mut flash, "storage", key, value)
+                .unwrap();
+        }
+        assert_eq!(
+            flash.data()[0x9040..0x9040 + 6 * 0x20],
+            NVS_ENC[0x40..0x40 + 6 * 0x20]
+        );
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "u32_key", &mut buffer),
+            Ok(Some(NvsValue::U32(u32::MAX)))
+        );
+    }
+}
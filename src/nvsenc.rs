@@ -0,0 +1,302 @@
+use crate::{utils, PartitionError};
+
+#[cfg(feature = "embedded-storage")]
+use crate::{DataPartitionType, NorFlashOpError, PartitionEntry, PartitionType};
+
+#[cfg(feature = "embedded-storage")]
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+
+#[cfg(feature = "aes")]
+use crate::NvsItem;
+
+#[cfg(feature = "aes")]
+use aes::{
+    cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyInit},
+    Aes256,
+};
+
+/// NVS encryption keys
+///
+/// The same as `nvs_sec_cfg_t` from ESP-IDF.
+///
+/// With flash encryption based scheme the keys are stored in `nvs_keys` partition
+/// which is protected by flash encryption.
+/// With HMAC based scheme the keys are derived from HMAC key stored in eFuse
+/// (see [`NvsKeys::from_hmac_key`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NvsKeys {
+    /// XTS encryption key
+    pub eky: [u8; NvsKeys::KEY_SIZE],
+
+    /// XTS tweak key
+    pub tky: [u8; NvsKeys::KEY_SIZE],
+}
+
+impl NvsKeys {
+    /// Size of single key in bytes
+    pub const KEY_SIZE: usize = 32;
+
+    /// Size of keys with CRC in bytes
+    pub const SIZE: usize = Self::KEY_SIZE * 2 + 4;
+
+    /// Seed to derive encryption key using HMAC
+    pub const HMAC_EKY_SEED: u32 = 0xaebe5a5a;
+
+    /// Seed to derive tweak key using HMAC
+    pub const HMAC_TKY_SEED: u32 = 0xcedea5a5;
+
+    /// Compute CRC32 of keys
+    pub fn calc_crc(&self) -> u32 {
+        utils::crc32_le(utils::crc32_le(u32::MAX, &self.eky), &self.tky)
+    }
+
+    /// Derive keys from HMAC key like HMAC based scheme does
+    ///
+    /// Each key is HMAC-SHA256 of corresponding seed repeated eight times.
+    #[cfg(feature = "hmac")]
+    pub fn from_hmac_key(key: &[u8; Self::KEY_SIZE]) -> Self {
+        use hmac::{Hmac, Mac};
+
+        let derive = |seed: u32| {
+            let mut mac = <Hmac<sha2::Sha256> as Mac>::new_from_slice(key).unwrap();
+            for _ in 0..8 {
+                mac.update(&seed.to_le_bytes());
+            }
+            mac.finalize().into_bytes().into()
+        };
+
+        Self {
+            eky: derive(Self::HMAC_EKY_SEED),
+            tky: derive(Self::HMAC_TKY_SEED),
+        }
+    }
+
+    /// Convert keys from binary representation
+    ///
+    /// Returns `None` when keys is not initialized (erased).
+    pub fn from_bytes(data: &[u8; Self::SIZE]) -> Result<Option<Self>, PartitionError> {
+        let (eky, data) = data.split_first_chunk().unwrap();
+        let (tky, data) = data.split_first_chunk().unwrap();
+        let (crc, _) = data.split_first_chunk::<4>().unwrap();
+
+        if eky.iter().all(|byte| *byte == 0xff) {
+            return Ok(None);
+        }
+
+        let keys = Self {
+            eky: *eky,
+            tky: *tky,
+        };
+        if u32::from_le_bytes(*crc) != keys.calc_crc() {
+            return Err(PartitionError::InvalidChecksum);
+        }
+
+        Ok(Some(keys))
+    }
+
+    /// Convert keys to binary representation
+    pub fn to_bytes(&self, data: &mut [u8; Self::SIZE]) {
+        let (eky, data) = data.split_first_chunk_mut().unwrap();
+        *eky = self.eky;
+        let (tky, data) = data.split_first_chunk_mut().unwrap();
+        *tky = self.tky;
+        let (crc, _) = data.split_first_chunk_mut::<4>().unwrap();
+        *crc = self.calc_crc().to_le_bytes();
+    }
+
+    /// Read keys from nvs_keys partition
+    ///
+    /// Returns `None` when keys is not initialized.
+    #[cfg(feature = "embedded-storage")]
+    pub fn read_nor_flash<S>(
+        storage: &mut S,
+        partition: &PartitionEntry,
+    ) -> Result<Option<Self>, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        check_partition(partition)?;
+
+        let mut data = [0; Self::SIZE];
+        storage
+            .read(partition.offset, &mut data)
+            .map_err(NorFlashOpError::StorageError)?;
+
+        Ok(Self::from_bytes(&data)?)
+    }
+
+    /// Write keys into nvs_keys partition
+    ///
+    /// The first sector of partition is erased before writing.
+    #[cfg(feature = "embedded-storage")]
+    pub fn write_nor_flash<S>(
+        &self,
+        storage: &mut S,
+        partition: &PartitionEntry,
+    ) -> Result<(), NorFlashOpError<S>>
+    where
+        S: NorFlash,
+    {
+        check_partition(partition)?;
+
+        let mut data = [0xff; 128];
+        let size = Self::SIZE.next_multiple_of(S::WRITE_SIZE);
+        if size > data.len() || partition.size < S::ERASE_SIZE {
+            return Err(PartitionError::InvalidAlignment.into());
+        }
+        self.to_bytes(data.first_chunk_mut().unwrap());
+
+        storage
+            .erase(partition.offset, partition.offset + S::ERASE_SIZE as u32)
+            .map_err(NorFlashOpError::StorageError)?;
+        storage
+            .write(partition.offset, &data[..size])
+            .map_err(NorFlashOpError::StorageError)
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+fn check_partition(partition: &PartitionEntry) -> Result<(), PartitionError> {
+    if partition.type_ != PartitionType::Data(DataPartitionType::NvsKeys) {
+        return Err(PartitionError::InvalidPartition);
+    }
+    if partition.size < NvsKeys::SIZE {
+        return Err(PartitionError::NotEnoughData);
+    }
+    Ok(())
+}
+
+/// NVS entries cipher
+///
+/// Entries are encrypted using AES-256-XTS with data unit of single entry.
+/// The tweak is an address of entry relative to the start of partition.
+#[cfg(feature = "aes")]
+#[derive(Clone)]
+pub struct NvsCipher {
+    data: Aes256,
+    tweak: Aes256,
+}
+
+#[cfg(feature = "aes")]
+impl NvsCipher {
+    /// Instantiate cipher using keys
+    pub fn new(keys: &NvsKeys) -> Self {
+        Self {
+            data: Aes256::new(&keys.eky.into()),
+            tweak: Aes256::new(&keys.tky.into()),
+        }
+    }
+
+    /// Encrypt entry with specified address
+    pub fn encrypt(&self, addr: u32, data: &mut [u8; NvsItem::SIZE]) {
+        self.apply(addr, data, |block| self.data.encrypt_block(block));
+    }
+
+    /// Decrypt entry with specified address
+    pub fn decrypt(&self, addr: u32, data: &mut [u8; NvsItem::SIZE]) {
+        self.apply(addr, data, |block| self.data.decrypt_block(block));
+    }
+
+    fn apply(
+        &self,
+        addr: u32,
+        data: &mut [u8; NvsItem::SIZE],
+        cipher: impl Fn(&mut GenericArray<u8, aes::cipher::consts::U16>),
+    ) {
+        let mut tweak = GenericArray::default();
+        tweak[..4].copy_from_slice(&addr.to_le_bytes());
+        self.tweak.encrypt_block(&mut tweak);
+
+        for block in data.chunks_exact_mut(16) {
+            let block = GenericArray::from_mut_slice(block);
+            block.iter_mut().zip(&tweak).for_each(|(b, t)| *b ^= t);
+            cipher(block);
+            block.iter_mut().zip(&tweak).for_each(|(b, t)| *b ^= t);
+
+            // Multiply tweak by primitive element in GF(2^128)
+            let carry = tweak[15] >> 7;
+            for index in (1..16).rev() {
+                tweak[index] = (tweak[index] << 1) | (tweak[index - 1] >> 7);
+            }
+            tweak[0] = (tweak[0] << 1) ^ (carry * 0x87);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    const KEYS: &[u8] = include_bytes!("../tests/nvs_keys.bin");
+
+    #[test]
+    fn keys() {
+        let keys = NvsKeys::from_bytes(KEYS.first_chunk().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(keys.eky[0], 0x00);
+        assert_eq!(keys.tky[0], 0x20);
+        assert_eq!(keys.calc_crc(), 0x9a7c5245);
+
+        let mut data = [0; NvsKeys::SIZE];
+        keys.to_bytes(&mut data);
+        assert_eq!(&data, KEYS.first_chunk().unwrap());
+
+        data[0] ^= 1;
+        assert_eq!(
+            NvsKeys::from_bytes(&data),
+            Err(PartitionError::InvalidChecksum)
+        );
+        assert_eq!(NvsKeys::from_bytes(&[0xff; NvsKeys::SIZE]), Ok(None));
+    }
+
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn hmac_keys() {
+        let key = core::array::from_fn(|index| 0xa0 + index as u8);
+        let keys = NvsKeys::from_hmac_key(&key);
+        assert_eq!(
+            keys.eky[..8],
+            [0xef, 0x8a, 0x3e, 0x7e, 0xf7, 0xa9, 0x29, 0x19]
+        );
+        assert_eq!(
+            keys.tky[24..],
+            [0xa3, 0x5f, 0x17, 0x50, 0xf5, 0x9f, 0x67, 0xc1]
+        );
+    }
+
+    #[cfg(feature = "aes")]
+    #[test]
+    fn cipher() {
+        const NVS: &[u8] = include_bytes!("../tests/nvs.bin");
+        const NVS_ENC: &[u8] = include_bytes!("../tests/nvs_enc.bin");
+
+        let keys = NvsKeys::from_bytes(KEYS.first_chunk().unwrap())
+            .unwrap()
+            .unwrap();
+        let cipher = NvsCipher::new(&keys);
+
+        let mut count = 0;
+        for page in 0..NVS.len() / NvsPartition::PAGE_SIZE {
+            let nvs = NvsPartition::new(0, NVS.len());
+            let states =
+                NvsEntryStates(*NVS[nvs.states_addr(page) as usize..].first_chunk().unwrap());
+            for index in 0..NvsPartition::ENTRY_COUNT {
+                if states.get(index) != NvsEntryState::Written {
+                    continue;
+                }
+                let addr = nvs.entry_addr(page, index);
+                let plain = NVS[addr as usize..].first_chunk().unwrap();
+                let encrypted = NVS_ENC[addr as usize..].first_chunk().unwrap();
+
+                let mut data = *plain;
+                cipher.encrypt(addr, &mut data);
+                assert_eq!(&data, encrypted);
+                cipher.decrypt(addr, &mut data);
+                assert_eq!(&data, plain);
+                count += 1;
+            }
+        }
+        assert!(count > 100);
+    }
+}
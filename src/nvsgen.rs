@@ -0,0 +1,958 @@
+use crate::{
+    csv::Fields, utils, CsvError, CsvErrorKind, NvsEntryState, NvsEntryStates, NvsItem,
+    NvsPageHeader, NvsPageState, NvsPartition, NvsType, NvsValue, PartitionError,
+};
+use core::{fmt, str::Lines};
+
+#[cfg(feature = "aes")]
+use crate::{NvsCipher, NvsKeys};
+
+/// Value encoding in NVS CSV
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NvsEncoding {
+    /// Unsigned 8-bit integer
+    U8,
+
+    /// Signed 8-bit integer
+    I8,
+
+    /// Unsigned 16-bit integer
+    U16,
+
+    /// Signed 16-bit integer
+    I16,
+
+    /// Unsigned 32-bit integer
+    U32,
+
+    /// Signed 32-bit integer
+    I32,
+
+    /// Unsigned 64-bit integer
+    U64,
+
+    /// Signed 64-bit integer
+    I64,
+
+    /// String
+    String,
+
+    /// Hex-encoded binary data
+    Hex2Bin,
+
+    /// Base64-encoded binary data
+    Base64,
+
+    /// Raw binary data
+    Binary,
+}
+
+impl NvsEncoding {
+    const NAMES: [(&'static str, Self); 12] = [
+        ("u8", Self::U8),
+        ("i8", Self::I8),
+        ("u16", Self::U16),
+        ("i16", Self::I16),
+        ("u32", Self::U32),
+        ("i32", Self::I32),
+        ("u64", Self::U64),
+        ("i64", Self::I64),
+        ("string", Self::String),
+        ("hex2bin", Self::Hex2Bin),
+        ("base64", Self::Base64),
+        ("binary", Self::Binary),
+    ];
+
+    /// Get encoding from name
+    ///
+    /// The name matching is case-insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, encoding)| *encoding)
+    }
+
+    /// Get encoding name
+    pub fn name(&self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find(|(_, encoding)| encoding == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+
+    /// Decode value
+    ///
+    /// The `buffer` is used to hold decoded binary data.
+    pub fn decode<'a>(
+        &self,
+        value: &'a [u8],
+        buffer: &'a mut [u8],
+    ) -> Result<NvsValue<'a>, CsvErrorKind> {
+        Ok(match self {
+            Self::U8 => NvsValue::U8(parse_int(value)?),
+            Self::I8 => NvsValue::I8(parse_int(value)?),
+            Self::U16 => NvsValue::U16(parse_int(value)?),
+            Self::I16 => NvsValue::I16(parse_int(value)?),
+            Self::U32 => NvsValue::U32(parse_int(value)?),
+            Self::I32 => NvsValue::I32(parse_int(value)?),
+            Self::U64 => NvsValue::U64(parse_int(value)?),
+            Self::I64 => NvsValue::I64(parse_int(value)?),
+            Self::String => NvsValue::Str(
+                core::str::from_utf8(value).map_err(|_| PartitionError::InvalidString)?,
+            ),
+            Self::Hex2Bin => NvsValue::Blob(decode_hex(utils::trim_ascii(value), buffer)?),
+            Self::Base64 => NvsValue::Blob(decode_base64(value, buffer)?),
+            Self::Binary => NvsValue::Blob(value),
+        })
+    }
+}
+
+impl fmt::Display for NvsEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+/// Parse integer like Python's `int(value)`
+fn parse_int<T: TryFrom<i128>>(value: &[u8]) -> Result<T, CsvErrorKind> {
+    core::str::from_utf8(utils::trim_ascii(value))
+        .ok()
+        .and_then(|value| value.parse::<i128>().ok())
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or(CsvErrorKind::InvalidNumber)
+}
+
+fn decode_hex<'b>(value: &[u8], buffer: &'b mut [u8]) -> Result<&'b [u8], CsvErrorKind> {
+    if value.len() % 2 != 0 {
+        return Err(CsvErrorKind::InvalidEncoding);
+    }
+    let buffer = buffer
+        .get_mut(..value.len() / 2)
+        .ok_or(PartitionError::TooManyData)?;
+
+    for (byte, digits) in buffer.iter_mut().zip(value.chunks_exact(2)) {
+        let digits = core::str::from_utf8(digits).map_err(|_| CsvErrorKind::InvalidEncoding)?;
+        *byte = u8::from_str_radix(digits, 16).map_err(|_| CsvErrorKind::InvalidEncoding)?;
+    }
+
+    Ok(buffer)
+}
+
+/// Decode base64 like Python's `binascii.a2b_base64(value)`
+///
+/// Characters outside of alphabet are ignored.
+fn decode_base64<'b>(value: &[u8], buffer: &'b mut [u8]) -> Result<&'b [u8], CsvErrorKind> {
+    let mut size = 0;
+    let mut bits = 0u32;
+    let mut count = 0;
+    let mut padded = false;
+
+    for char in value {
+        let digit = match char {
+            b'A'..=b'Z' => char - b'A',
+            b'a'..=b'z' => char - b'a' + 26,
+            b'0'..=b'9' => char - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' if count >= 2 => {
+                padded = true;
+                break;
+            }
+            _ => continue,
+        };
+
+        bits = bits << 6 | digit as u32;
+        count += 1;
+        if count == 4 {
+            let bytes = buffer
+                .get_mut(size..size + 3)
+                .ok_or(PartitionError::TooManyData)?;
+            bytes.copy_from_slice(&bits.to_be_bytes()[1..]);
+            size += 3;
+            bits = 0;
+            count = 0;
+        }
+    }
+
+    if count > 0 {
+        if count == 1 || !padded {
+            return Err(CsvErrorKind::InvalidEncoding);
+        }
+        let tail = count - 1;
+        bits <<= 6 * (4 - count);
+        let bytes = buffer
+            .get_mut(size..size + tail)
+            .ok_or(PartitionError::TooManyData)?;
+        bytes.copy_from_slice(&bits.to_be_bytes()[1..][..tail]);
+        size += tail;
+    }
+
+    Ok(&buffer[..size])
+}
+
+/// Kind of NVS CSV entry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NvsCsvKind {
+    /// Namespace definition
+    Namespace,
+
+    /// Value in specified encoding
+    Data(NvsEncoding),
+
+    /// Path to file with value in specified encoding
+    File(NvsEncoding),
+}
+
+/// Entry of NVS CSV
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NvsCsvEntry<'t> {
+    /// Key or namespace name
+    pub key: &'t str,
+
+    /// Entry kind
+    pub kind: NvsCsvKind,
+
+    /// Value or file path
+    pub value: &'t str,
+
+    line: usize,
+    column: usize,
+}
+
+impl NvsCsvEntry<'_> {
+    /// Create error which points to the value
+    pub fn error(&self, kind: impl Into<CsvErrorKind>) -> CsvError {
+        CsvError {
+            line: self.line,
+            column: self.column,
+            kind: kind.into(),
+        }
+    }
+}
+
+/// Iterator over entries of NVS CSV
+///
+/// The first line which is not a comment is a header with `key`, `type`, `encoding` and `value` columns.
+#[derive(Clone)]
+pub struct NvsCsvIter<'t> {
+    lines: Lines<'t>,
+    line: usize,
+    columns: Option<[usize; 4]>,
+    done: bool,
+}
+
+impl<'t> NvsCsvIter<'t> {
+    const COLUMNS: [&'static str; 4] = ["key", "type", "encoding", "value"];
+
+    /// Create iterator over entries of CSV text
+    pub fn new(text: &'t str) -> Self {
+        Self {
+            lines: text.lines(),
+            line: 0,
+            columns: None,
+            done: false,
+        }
+    }
+
+    /// Get number of last processed line
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Read next entry
+    ///
+    /// Returns `None` when end of text reached.
+    pub fn next_entry(&mut self) -> Result<Option<NvsCsvEntry<'t>>, CsvError> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let result = self.read_entry();
+
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+
+        result
+    }
+
+    fn read_entry(&mut self) -> Result<Option<NvsCsvEntry<'t>>, CsvError> {
+        loop {
+            let Some(line) = self.lines.next() else {
+                return Ok(None);
+            };
+            self.line += 1;
+
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = Fields::new(line, self.line);
+
+            let Some(columns) = self.columns else {
+                self.columns = Some(Self::read_header(&mut fields)?);
+                continue;
+            };
+
+            // Collect key, type, encoding and value with positions
+            let mut values = [("", fields.error(CsvErrorKind::EmptyField)); 4];
+            let last = columns.iter().copied().max().unwrap_or_default();
+            for index in 0..=last {
+                let field = fields.next();
+                for (column, value) in columns.iter().zip(values.iter_mut()) {
+                    if *column == index {
+                        *value = (field, fields.error(CsvErrorKind::EmptyField));
+                    }
+                }
+            }
+            let [(key, key_error), (type_, type_error), (encoding, encoding_error), (value, value_error)] =
+                values;
+
+            if key.is_empty() {
+                return Err(key_error);
+            }
+            if key.len() > NvsItem::MAX_KEY_LEN {
+                return Err(CsvError {
+                    kind: PartitionError::InvalidString.into(),
+                    ..key_error
+                });
+            }
+
+            let kind = if type_.eq_ignore_ascii_case("namespace") {
+                NvsCsvKind::Namespace
+            } else {
+                let encoding = NvsEncoding::from_name(encoding).ok_or(CsvError {
+                    kind: CsvErrorKind::InvalidEncoding,
+                    ..encoding_error
+                })?;
+
+                if type_.eq_ignore_ascii_case("data") {
+                    NvsCsvKind::Data(encoding)
+                } else if type_.eq_ignore_ascii_case("file") {
+                    if value.is_empty() {
+                        return Err(value_error);
+                    }
+                    NvsCsvKind::File(encoding)
+                } else {
+                    return Err(CsvError {
+                        kind: CsvErrorKind::InvalidType,
+                        ..type_error
+                    });
+                }
+            };
+
+            return Ok(Some(NvsCsvEntry {
+                key,
+                kind,
+                value,
+                line: value_error.line,
+                column: value_error.column,
+            }));
+        }
+    }
+
+    fn read_header(fields: &mut Fields) -> Result<[usize; 4], CsvError> {
+        let mut columns = [None; 4];
+
+        for index in 0.. {
+            let name = fields.next();
+            if name.is_empty() {
+                break;
+            }
+            if let Some(position) = Self::COLUMNS.iter().position(|known| *known == name) {
+                columns[position] = Some(index);
+            }
+        }
+
+        let mut result = [0; 4];
+        for (column, index) in result.iter_mut().zip(columns) {
+            *column = index.ok_or(fields.error(CsvErrorKind::EmptyField))?;
+        }
+        Ok(result)
+    }
+}
+
+impl<'t> Iterator for NvsCsvIter<'t> {
+    type Item = Result<NvsCsvEntry<'t>, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().transpose()
+    }
+}
+
+/// NVS partition image writer
+///
+/// Produces the same images as `nvs_partition_gen.py` from ESP-IDF.
+/// The last page is left erased as reserved for page reclaim.
+pub struct NvsImageWriter<'b> {
+    data: &'b mut [u8],
+    page: usize,
+    entry: usize,
+    namespace: u8,
+    #[cfg(feature = "aes")]
+    cipher: Option<NvsCipher>,
+}
+
+impl NvsPartition {
+    /// Minimum size of partition supported by image writer
+    pub const MIN_IMAGE_SIZE: usize = 3 * Self::PAGE_SIZE;
+
+    /// Create image writer for partition
+    ///
+    /// The image occupies the beginning of `data` which should be not less than partition size.
+    /// Entries is encrypted when partition has keys.
+    pub fn image_writer<'b>(
+        &self,
+        data: &'b mut [u8],
+    ) -> Result<NvsImageWriter<'b>, PartitionError> {
+        let data = data
+            .get_mut(..self.size)
+            .ok_or(PartitionError::NotEnoughData)?;
+        #[allow(unused_mut)]
+        let mut writer = NvsImageWriter::new(data)?;
+        #[cfg(feature = "aes")]
+        if let Some(keys) = self.keys() {
+            writer = writer.with_keys(keys);
+        }
+        Ok(writer)
+    }
+}
+
+impl<'b> NvsImageWriter<'b> {
+    /// Maximum size of string data or single blob chunk
+    pub const MAX_DATA_SIZE: usize = (NvsPartition::ENTRY_COUNT - 1) * NvsItem::SIZE;
+
+    /// Create image writer which fills whole `data`
+    pub fn new(data: &'b mut [u8]) -> Result<Self, PartitionError> {
+        if data.len() % NvsPartition::PAGE_SIZE != 0 {
+            return Err(PartitionError::InvalidAlignment);
+        }
+        if data.len() < NvsPartition::MIN_IMAGE_SIZE {
+            return Err(PartitionError::NotEnoughSpace);
+        }
+
+        data.fill(0xff);
+        let mut writer = Self {
+            data,
+            page: 0,
+            entry: 0,
+            namespace: 0,
+            #[cfg(feature = "aes")]
+            cipher: None,
+        };
+        writer.init_page();
+
+        Ok(writer)
+    }
+
+    /// Get maximum size of blob data which can be written into image
+    ///
+    /// Useful to allocate buffer for [`NvsImageWriter::add_csv`].
+    pub fn max_blob_size(&self) -> usize {
+        // Last page is reserved
+        (self.data.len() / NvsPartition::PAGE_SIZE - 1) * Self::MAX_DATA_SIZE
+    }
+
+    /// Use keys to encrypt entries
+    ///
+    /// Should be set before adding any values.
+    #[cfg(feature = "aes")]
+    pub fn with_keys(self, keys: &NvsKeys) -> Self {
+        Self {
+            cipher: Some(NvsCipher::new(keys)),
+            ..self
+        }
+    }
+
+    /// Get page data
+    fn page_data(&mut self) -> &mut [u8] {
+        &mut self.data[self.page * NvsPartition::PAGE_SIZE..][..NvsPartition::PAGE_SIZE]
+    }
+
+    fn init_page(&mut self) {
+        let header = NvsPageHeader::new(
+            NvsPageState::Active,
+            self.page as u32,
+            NvsPageHeader::VERSION2,
+        );
+        header.to_bytes(self.page_data().first_chunk_mut().unwrap());
+    }
+
+    /// Mark current page as full and initialize next one
+    fn new_page(&mut self) -> Result<(), PartitionError> {
+        let state = self.page_data().first_chunk_mut::<4>().unwrap();
+        if NvsPageState::from(u32::from_le_bytes(*state)) == NvsPageState::Active {
+            *state = u32::from(NvsPageState::Full).to_le_bytes();
+        }
+
+        // Last page is reserved
+        if self.page + 2 >= self.data.len() / NvsPartition::PAGE_SIZE {
+            return Err(PartitionError::NotEnoughSpace);
+        }
+
+        self.page += 1;
+        self.entry = 0;
+        self.init_page();
+
+        Ok(())
+    }
+
+    /// Write item followed by data into current page
+    fn write_item(&mut self, mut item: NvsItem, data: &[&[u8]]) {
+        item.seal(data);
+
+        let entry = self.entry;
+        let base = self.page * NvsPartition::PAGE_SIZE;
+        let page = &mut self.data[base..][..NvsPartition::PAGE_SIZE];
+        let mut states = NvsEntryStates(*page[NvsPageHeader::SIZE..].first_chunk().unwrap());
+
+        for (index, data) in (entry..).zip(item.entries(data)) {
+            let offset = NvsPageHeader::SIZE + states.0.len() + index * NvsItem::SIZE;
+            #[cfg(feature = "aes")]
+            let data = self.cipher.as_ref().map_or(data, |cipher| {
+                let mut data = data;
+                cipher.encrypt((base + offset) as u32, &mut data);
+                data
+            });
+            page[offset..][..NvsItem::SIZE].copy_from_slice(&data);
+            states.set(index, NvsEntryState::Written);
+        }
+
+        page[NvsPageHeader::SIZE..][..states.0.len()].copy_from_slice(&states.0);
+        self.entry += item.span as usize;
+    }
+
+    /// Add namespace
+    ///
+    /// Following values will be added to this namespace.
+    /// Returns index of namespace.
+    pub fn add_namespace(&mut self, name: &str) -> Result<u8, PartitionError> {
+        let ns = self
+            .namespace
+            .checked_add(1)
+            .filter(|ns| *ns != NvsItem::CHUNK_ANY)
+            .ok_or(PartitionError::TooManyData)?;
+        let mut item = NvsItem::new(0, NvsType::U8, name)?;
+        item.data[0] = ns;
+
+        if self.entry >= NvsPartition::ENTRY_COUNT {
+            self.new_page()?;
+        }
+        self.write_item(item, &[]);
+        self.namespace = ns;
+
+        Ok(ns)
+    }
+
+    /// Add value to current namespace
+    ///
+    /// Binary data is written as multi-page blob.
+    pub fn add(&mut self, key: &str, value: NvsValue<'_>) -> Result<(), PartitionError> {
+        if self.namespace == 0 {
+            // Namespace should be added first
+            return Err(PartitionError::InvalidPartition);
+        }
+        let mut item = NvsItem::new(self.namespace, value.type_(), key)?;
+
+        match value {
+            NvsValue::Str(value) => {
+                let size = value.len() + 1;
+                if size > Self::MAX_DATA_SIZE {
+                    return Err(PartitionError::TooManyData);
+                }
+                if self.entry + 1 + size.div_ceil(NvsItem::SIZE) >= NvsPartition::ENTRY_COUNT {
+                    self.new_page()?;
+                }
+                self.write_item(item, &[value.as_bytes(), b"\0"]);
+            }
+            NvsValue::Blob(value) => {
+                if self.entry >= NvsPartition::ENTRY_COUNT {
+                    self.new_page()?;
+                }
+                self.write_blob(item, value)?;
+            }
+            _ => {
+                item.data = value.to_item_data().unwrap();
+                if self.entry >= NvsPartition::ENTRY_COUNT {
+                    self.new_page()?;
+                }
+                self.write_item(item, &[]);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_blob(&mut self, item: NvsItem, data: &[u8]) -> Result<(), PartitionError> {
+        let mut offset = 0;
+        let mut chunk_count = 0;
+
+        loop {
+            // Chunk indexes from 0x80 belong to alternate blob version
+            if chunk_count >= 0x80 {
+                return Err(PartitionError::TooManyData);
+            }
+            let tailroom = (NvsPartition::ENTRY_COUNT - self.entry - 1) * NvsItem::SIZE;
+            let size = tailroom.min(data.len() - offset);
+
+            let chunk = NvsItem {
+                type_: NvsType::BlobData,
+                chunk_index: chunk_count,
+                ..item
+            };
+            self.write_item(chunk, &[&data[offset..offset + size]]);
+            chunk_count += 1;
+            offset += size;
+
+            if offset < data.len() || tailroom - size < NvsItem::SIZE {
+                self.new_page()?;
+            }
+            if offset == data.len() {
+                break;
+            }
+        }
+
+        let mut index = item;
+        index.data[..4].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        index.data[4] = chunk_count;
+        index.data[5] = 0;
+        self.write_item(index, &[]);
+
+        Ok(())
+    }
+
+    /// Add entries from CSV text
+    ///
+    /// The `buffer` is used to decode `hex2bin` and `base64` values, it should be not less
+    /// than [`NvsImageWriter::max_blob_size`] bytes to fit any blob which fits into image.
+    /// The values which don't fit into smaller buffer are reported as
+    /// [`PartitionError::TooManyData`].
+    /// The `read_file` is used to get contents of files referenced by file entries.
+    pub fn add_csv<'f>(
+        &mut self,
+        text: &str,
+        buffer: &mut [u8],
+        mut read_file: impl FnMut(&str) -> Option<&'f [u8]>,
+    ) -> Result<(), CsvError> {
+        for entry in NvsCsvIter::new(text) {
+            let entry = entry?;
+
+            let (encoding, value) = match entry.kind {
+                NvsCsvKind::Namespace => {
+                    self.add_namespace(entry.key)
+                        .map_err(|error| entry.error(error))?;
+                    continue;
+                }
+                NvsCsvKind::Data(encoding) => (encoding, entry.value.as_bytes()),
+                NvsCsvKind::File(encoding) => (
+                    encoding,
+                    read_file(entry.value).ok_or(entry.error(PartitionError::NotEnoughData))?,
+                ),
+            };
+
+            let value = encoding
+                .decode(value, buffer)
+                .map_err(|kind| entry.error(kind))?;
+            self.add(entry.key, value)
+                .map_err(|error| entry.error(error))?;
+        }
+
+        Ok(())
+    }
+
+    /// Finish image
+    ///
+    /// The remaining pages are initialized and marked as full.
+    pub fn finish(mut self) {
+        while self.new_page().is_ok() {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    const CSV: &str = include_str!("../tests/nvs.csv");
+    const NVS: &[u8; 0x5000] = include_bytes!("../tests/nvs.bin");
+
+    #[test]
+    fn encodings() {
+        let mut buffer = [0; 16];
+
+        assert_eq!(
+            NvsEncoding::from_name("HEX2BIN"),
+            Some(NvsEncoding::Hex2Bin)
+        );
+        assert_eq!(NvsEncoding::from_name("hex"), None);
+        assert_eq!(NvsEncoding::Base64.name(), "base64");
+
+        assert_eq!(
+            NvsEncoding::U8.decode(b" 255 ", &mut buffer),
+            Ok(NvsValue::U8(255))
+        );
+        assert_eq!(
+            NvsEncoding::U8.decode(b"256", &mut buffer),
+            Err(CsvErrorKind::InvalidNumber)
+        );
+        assert_eq!(
+            NvsEncoding::I64.decode(b"-9223372036854775808", &mut buffer),
+            Ok(NvsValue::I64(i64::MIN))
+        );
+        assert_eq!(
+            NvsEncoding::U32.decode(b"0x10", &mut buffer),
+            Err(CsvErrorKind::InvalidNumber)
+        );
+        assert_eq!(
+            NvsEncoding::Hex2Bin.decode(b"00ff7A", &mut buffer),
+            Ok(NvsValue::Blob(&[0x00, 0xff, 0x7a]))
+        );
+        assert_eq!(
+            NvsEncoding::Hex2Bin.decode(b"0ff", &mut buffer),
+            Err(CsvErrorKind::InvalidEncoding)
+        );
+        assert_eq!(
+            NvsEncoding::Base64.decode(b"SGVsbG8=", &mut buffer),
+            Ok(NvsValue::Blob(b"Hello"))
+        );
+        assert_eq!(
+            NvsEncoding::Base64.decode(b"SGVs\nbG8h", &mut buffer),
+            Ok(NvsValue::Blob(b"Hello!"))
+        );
+        assert_eq!(
+            NvsEncoding::Base64.decode(b"SGVsbG8", &mut buffer),
+            Err(CsvErrorKind::InvalidEncoding)
+        );
+        assert_eq!(
+            NvsEncoding::Base64.decode(b"SGVsbG8hSGVsbG8hSGVsbG8h", &mut buffer),
+            Err(CsvErrorKind::PartitionError(PartitionError::TooManyData))
+        );
+        assert_eq!(
+            NvsEncoding::String.decode(b"text", &mut buffer),
+            Ok(NvsValue::Str("text"))
+        );
+    }
+
+    #[test]
+    fn parse_csv() {
+        let mut iter = NvsCsvIter::new(
+            "# comment
+key,type,encoding,value
+
+storage,namespace,,
+name,data,string,Hello
+blob,file,binary,blob.bin
+",
+        );
+
+        let entry = iter.next().unwrap().unwrap();
+        assert_eq!(entry.key, "storage");
+        assert_eq!(entry.kind, NvsCsvKind::Namespace);
+        let entry = iter.next().unwrap().unwrap();
+        assert_eq!(entry.kind, NvsCsvKind::Data(NvsEncoding::String));
+        assert_eq!(entry.value, "Hello");
+        let entry = iter.next().unwrap().unwrap();
+        assert_eq!(entry.kind, NvsCsvKind::File(NvsEncoding::Binary));
+        assert_eq!(entry.value, "blob.bin");
+        assert_eq!(
+            entry.error(CsvErrorKind::EmptyField),
+            CsvError {
+                line: 6,
+                column: 18,
+                kind: CsvErrorKind::EmptyField
+            }
+        );
+        assert!(iter.next().is_none());
+
+        let mut iter = NvsCsvIter::new("value,encoding,key,type\n1,u8,key,data\n");
+        let entry = iter.next().unwrap().unwrap();
+        assert_eq!(entry.key, "key");
+        assert_eq!(entry.kind, NvsCsvKind::Data(NvsEncoding::U8));
+        assert_eq!(entry.value, "1");
+
+        let error = |text| NvsCsvIter::new(text).find_map(Result::err).unwrap();
+        assert_eq!(
+            error("key,type,encoding\n"),
+            CsvError {
+                line: 1,
+                column: 18,
+                kind: CsvErrorKind::EmptyField
+            }
+        );
+        assert_eq!(
+            error("key,type,encoding,value\nkey,data,u128,1\n"),
+            CsvError {
+                line: 2,
+                column: 10,
+                kind: CsvErrorKind::InvalidEncoding
+            }
+        );
+        assert_eq!(
+            error("key,type,encoding,value\nkey,blob,u8,1\n"),
+            CsvError {
+                line: 2,
+                column: 5,
+                kind: CsvErrorKind::InvalidType
+            }
+        );
+        assert_eq!(
+            error("key,type,encoding,value\nvery_long_key_name,data,u8,1\n"),
+            CsvError {
+                line: 2,
+                column: 1,
+                kind: PartitionError::InvalidString.into()
+            }
+        );
+    }
+
+    #[test]
+    fn write_image() {
+        let partition =
+            PartitionEntry::new(DataPartitionType::Nvs, 0x9000, NVS.len(), "nvs", false).unwrap();
+        let nvs = NvsPartition::try_from(&partition).unwrap();
+
+        let mut buffer = [0; NvsImageWriter::MAX_DATA_SIZE];
+        let mut data = [0; 0x6000];
+        let mut writer = nvs.image_writer(&mut data).unwrap();
+        writer.add_csv(CSV, &mut buffer, |_| None).unwrap();
+        writer.finish();
+        assert_eq!(data[..NVS.len()], NVS[..]);
+
+        let mut data = [0; 0x5000];
+        let mut writer = NvsImageWriter::new(&mut data[..0x3000]).unwrap();
+        writer.add_csv(CSV, &mut buffer, |_| None).unwrap();
+        assert_eq!(
+            writer.add_csv(CSV, &mut buffer, |_| None),
+            Err(CsvError {
+                line: 17,
+                column: 23,
+                kind: PartitionError::NotEnoughSpace.into()
+            })
+        );
+
+        let mut writer = NvsImageWriter::new(&mut data).unwrap();
+        assert_eq!(
+            writer.add("key", NvsValue::U8(1)),
+            Err(PartitionError::InvalidPartition)
+        );
+        assert_eq!(
+            writer.add_csv(
+                "key,type,encoding,value\nfiles,namespace,,\nblob,file,binary,blob.bin\n",
+                &mut buffer,
+                |path| (path == "blob.bin").then_some(b"\x01\x02\x03".as_slice()),
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            writer.add_csv(
+                "key,type,encoding,value\nblob,file,binary,none.bin\n",
+                &mut buffer,
+                |_| None
+            ),
+            Err(CsvError {
+                line: 2,
+                column: 18,
+                kind: PartitionError::NotEnoughData.into()
+            })
+        );
+        writer.finish();
+
+        let item = NvsItem::from_bytes(data[64 + 32..].first_chunk().unwrap());
+        assert_eq!(item.type_, NvsType::BlobData);
+        assert_eq!(item.key(), "blob");
+        assert_eq!(item.data_size(), 3);
+        assert_eq!(data[64 + 64..][..4], [1, 2, 3, 0xff]);
+
+        assert_eq!(
+            NvsImageWriter::new(&mut data[..0x2000]).err(),
+            Some(PartitionError::NotEnoughSpace)
+        );
+        assert_eq!(
+            NvsImageWriter::new(&mut data[..0x3100]).err(),
+            Some(PartitionError::InvalidAlignment)
+        );
+        assert_eq!(
+            nvs.image_writer(&mut data[..0x4000]).err(),
+            Some(PartitionError::NotEnoughData)
+        );
+    }
+
+    #[cfg(feature = "embedded-storage")]
+    #[test]
+    fn write_multi_page_blob() {
+        use core::fmt::Write;
+
+        struct Text([u8; 0x6000], usize);
+
+        impl Write for Text {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                self.0[self.1..][..s.len()].copy_from_slice(s.as_bytes());
+                self.1 += s.len();
+                Ok(())
+            }
+        }
+
+        let mut blob = [0; 10000];
+        for (index, byte) in blob.iter_mut().enumerate() {
+            *byte = (index * 13 + 7) as u8;
+        }
+
+        let mut text = Text([0; 0x6000], 0);
+        text.write_str("key,type,encoding,value\nstorage,namespace,,\nblob,data,hex2bin,")
+            .unwrap();
+        for byte in &blob {
+            write!(text, "{byte:02x}").unwrap();
+        }
+        text.write_str("\n").unwrap();
+        let text = core::str::from_utf8(&text.0[..text.1]).unwrap();
+
+        let nvs = NvsPartition::new(0, 0x6000);
+        let mut data = [0; 0x6000];
+        let mut writer = nvs.image_writer(&mut data).unwrap();
+        let mut buffer = [0; 5 * NvsImageWriter::MAX_DATA_SIZE];
+        assert_eq!(writer.max_blob_size(), buffer.len());
+        writer.add_csv(text, &mut buffer, |_| None).unwrap();
+        writer.finish();
+
+        let mut flash = NorFlashSim::<_>::new(data);
+        let mut buffer = [0; 10000];
+        assert_eq!(
+            nvs.get_nor_flash(&mut flash, "storage", "blob", &mut buffer),
+            Ok(Some(NvsValue::Blob(&blob)))
+        );
+        // namespace, three data chunks and blob index
+        assert_eq!(nvs.iter_nor_flash(&mut flash).count(), 5);
+
+        let mut data = [0; 0x3000];
+        let mut writer = NvsImageWriter::new(&mut data).unwrap();
+        let mut buffer = [0; 10000];
+        assert_eq!(
+            writer.add_csv(text, &mut buffer, |_| None),
+            Err(CsvError {
+                line: 3,
+                column: 19,
+                kind: PartitionError::NotEnoughSpace.into()
+            })
+        );
+    }
+
+    #[cfg(feature = "aes")]
+    #[test]
+    fn write_encrypted_image() {
+        const NVS_ENC: &[u8] = include_bytes!("../tests/nvs_enc.bin");
+        let keys = include_bytes!("../tests/nvs_keys.bin");
+        let keys = NvsKeys::from_bytes(keys.first_chunk().unwrap())
+            .unwrap()
+            .unwrap();
+
+        let nvs = NvsPartition::new(0x9000, NVS_ENC.len()).with_keys(keys);
+        let mut buffer = [0; NvsImageWriter::MAX_DATA_SIZE];
+        let mut data = [0; 0x5000];
+        let mut writer = nvs.image_writer(&mut data).unwrap();
+        writer.add_csv(CSV, &mut buffer, |_| None).unwrap();
+        writer.finish();
+        assert_eq!(data[..], NVS_ENC[..]);
+    }
+}
@@ -0,0 +1,492 @@
+use crate::{utils, AppPartitionType, PartitionEntry, PartitionError, PartitionType};
+
+#[cfg(feature = "embedded-storage")]
+use crate::{DataPartitionType, NorFlashOpError};
+
+#[cfg(feature = "embedded-storage")]
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+
+/// OTA application image state
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OtaImageState {
+    /// Application was just selected to boot
+    New,
+
+    /// Application is booted first time and waits for confirmation
+    PendingVerify,
+
+    /// Application is confirmed as workable
+    Valid,
+
+    /// Application is marked as not workable
+    Invalid,
+
+    /// Application was not confirmed and rolled back
+    Aborted,
+
+    /// Application state is not used
+    #[default]
+    Undefined,
+
+    /// Unknown state value
+    Other(u32),
+}
+
+impl From<u32> for OtaImageState {
+    fn from(raw: u32) -> Self {
+        match raw {
+            0x0 => Self::New,
+            0x1 => Self::PendingVerify,
+            0x2 => Self::Valid,
+            0x3 => Self::Invalid,
+            0x4 => Self::Aborted,
+            u32::MAX => Self::Undefined,
+            _ => Self::Other(raw),
+        }
+    }
+}
+
+impl From<OtaImageState> for u32 {
+    fn from(state: OtaImageState) -> Self {
+        use OtaImageState::*;
+        match state {
+            New => 0x0,
+            PendingVerify => 0x1,
+            Valid => 0x2,
+            Invalid => 0x3,
+            Aborted => 0x4,
+            Undefined => u32::MAX,
+            Other(raw) => raw,
+        }
+    }
+}
+
+/// OTA selection entry
+///
+/// The same as `esp_ota_select_entry_t` from ESP-IDF.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OtaSelectEntry {
+    /// Sequence number
+    pub seq: u32,
+
+    /// Sequence label (unused by ESP-IDF)
+    pub label: [u8; OtaSelectEntry::LABEL_LEN],
+
+    /// Application image state
+    pub state: OtaImageState,
+
+    /// CRC32 of sequence number
+    pub crc: u32,
+}
+
+impl Default for OtaSelectEntry {
+    /// Erased entry
+    fn default() -> Self {
+        Self {
+            seq: u32::MAX,
+            label: [0xff; Self::LABEL_LEN],
+            state: OtaImageState::Undefined,
+            crc: u32::MAX,
+        }
+    }
+}
+
+impl OtaSelectEntry {
+    /// Size of entry in bytes
+    pub const SIZE: usize = 32;
+
+    /// Length of sequence label
+    pub const LABEL_LEN: usize = 20;
+
+    /// Compute CRC32 of entry
+    pub fn calc_crc(&self) -> u32 {
+        utils::crc32_le(u32::MAX, &self.seq.to_le_bytes())
+    }
+
+    /// Check that entry can be used to select application
+    ///
+    /// The entry is valid when sequence number is set, CRC is correct and
+    /// the application is not marked as invalid or aborted.
+    pub fn is_valid(&self) -> bool {
+        self.seq != u32::MAX
+            && !matches!(self.state, OtaImageState::Invalid | OtaImageState::Aborted)
+            && self.crc == self.calc_crc()
+    }
+
+    /// Convert entry from binary representation
+    pub fn from_bytes(data: &[u8; Self::SIZE]) -> Self {
+        let (seq, data) = data.split_first_chunk::<4>().unwrap();
+        let (label, data) = data.split_first_chunk().unwrap();
+        let (state, data) = data.split_first_chunk::<4>().unwrap();
+        let (crc, _) = data.split_first_chunk::<4>().unwrap();
+
+        Self {
+            seq: u32::from_le_bytes(*seq),
+            label: *label,
+            state: u32::from_le_bytes(*state).into(),
+            crc: u32::from_le_bytes(*crc),
+        }
+    }
+
+    /// Convert entry to binary representation
+    pub fn to_bytes(&self, data: &mut [u8; Self::SIZE]) {
+        let (seq, data) = data.split_first_chunk_mut::<4>().unwrap();
+        *seq = self.seq.to_le_bytes();
+        let (label, data) = data.split_first_chunk_mut().unwrap();
+        *label = self.label;
+        let (state, data) = data.split_first_chunk_mut::<4>().unwrap();
+        *state = u32::from(self.state).to_le_bytes();
+        let (crc, _) = data.split_first_chunk_mut::<4>().unwrap();
+        *crc = self.crc.to_le_bytes();
+    }
+}
+
+/// OTA data partition contents
+///
+/// The partition consists of two sectors with selection entry at the start of each.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct OtaData {
+    /// Selection entries
+    pub entries: [OtaSelectEntry; 2],
+}
+
+impl OtaData {
+    /// Size of sector which holds single entry
+    pub const SECTOR_SIZE: usize = 0x1000;
+
+    /// Size of OTA data
+    pub const SIZE: usize = Self::SECTOR_SIZE * 2;
+
+    /// Count OTA application slots
+    ///
+    /// Like ESP-IDF only slots which goes in order starting from `ota_0` are counted.
+    pub fn count_slots(partitions: impl IntoIterator<Item = impl AsRef<PartitionEntry>>) -> usize {
+        let mut slots = 0u16;
+        for partition in partitions {
+            if let PartitionType::App(AppPartitionType::Ota(number @ 0x00..=0x0f)) =
+                partition.as_ref().type_
+            {
+                slots |= 1 << number;
+            }
+        }
+        slots.trailing_ones() as usize
+    }
+
+    /// Get index of active entry
+    ///
+    /// When both entries are valid the one with greater sequence number is active.
+    pub fn active(&self) -> Option<usize> {
+        match self.entries.each_ref().map(OtaSelectEntry::is_valid) {
+            [true, true] if self.entries[1].seq > self.entries[0].seq => Some(1),
+            [true, _] => Some(0),
+            [false, true] => Some(1),
+            [false, false] => None,
+        }
+    }
+
+    /// Get OTA application slot which will be booted
+    ///
+    /// Returns `None` when no valid entries found, in that case bootloader boots
+    /// factory application.
+    pub fn boot_slot(&self, ota_count: usize) -> Option<AppPartitionType> {
+        let seq = self.entries[self.active()?].seq;
+        if ota_count == 0 {
+            return None;
+        }
+        Some(AppPartitionType::Ota(
+            (seq.wrapping_sub(1) % ota_count as u32) as u8,
+        ))
+    }
+
+    /// Select OTA application slot to boot
+    ///
+    /// The sequence number is selected the same way as `esp_ota_set_boot_partition` does.
+    /// The `state` should be [`OtaImageState::New`] when application rollback is enabled
+    /// and [`OtaImageState::Undefined`] otherwise.
+    ///
+    /// Returns index of updated entry which should be written.
+    /// When the next sequence number doesn't fit into `u32` the
+    /// [`PartitionError::SequenceOverflow`] will be returned.
+    pub fn set_boot_slot(
+        &mut self,
+        slot: u8,
+        ota_count: usize,
+        state: OtaImageState,
+    ) -> Result<usize, PartitionError> {
+        if slot as usize >= ota_count || slot > 0x0f {
+            return Err(PartitionError::InvalidOtaNumber(slot));
+        }
+        let (slot, count) = (slot as u32, ota_count as u32);
+
+        let (index, seq) = if let Some(active) = self.active() {
+            let seq = self.entries[active].seq;
+            let base = (slot + 1) % count;
+            let round = if seq > base {
+                (seq - base).div_ceil(count)
+            } else {
+                0
+            };
+            let seq = round
+                .checked_mul(count)
+                .and_then(|seq| seq.checked_add(base))
+                .filter(|seq| *seq != u32::MAX)
+                .ok_or(PartitionError::SequenceOverflow)?;
+            (active ^ 1, seq)
+        } else {
+            (0, slot + 1)
+        };
+
+        let entry = &mut self.entries[index];
+        entry.seq = seq;
+        entry.state = state;
+        entry.crc = entry.calc_crc();
+
+        Ok(index)
+    }
+
+    /// Convert OTA data from binary representation of both entries
+    pub fn from_bytes(data: [&[u8; OtaSelectEntry::SIZE]; 2]) -> Self {
+        Self {
+            entries: data.map(OtaSelectEntry::from_bytes),
+        }
+    }
+
+    /// Read OTA data from otadata partition
+    #[cfg(feature = "embedded-storage")]
+    pub fn read_nor_flash<S>(
+        storage: &mut S,
+        partition: &PartitionEntry,
+    ) -> Result<Self, NorFlashOpError<S>>
+    where
+        S: ReadNorFlash,
+    {
+        check_partition(partition)?;
+
+        let mut data = [[0; OtaSelectEntry::SIZE]; 2];
+        for (index, data) in data.iter_mut().enumerate() {
+            storage
+                .read(partition.offset + (Self::SECTOR_SIZE * index) as u32, data)
+                .map_err(NorFlashOpError::StorageError)?;
+        }
+
+        Ok(Self::from_bytes(data.each_ref()))
+    }
+
+    /// Write entry with specified index into otadata partition
+    ///
+    /// The sector which holds entry is erased before writing.
+    #[cfg(feature = "embedded-storage")]
+    pub fn write_nor_flash<S>(
+        &self,
+        storage: &mut S,
+        partition: &PartitionEntry,
+        index: usize,
+    ) -> Result<(), NorFlashOpError<S>>
+    where
+        S: NorFlash,
+    {
+        check_partition(partition)?;
+        let entry = self.entries.get(index).ok_or(PartitionError::TooManyData)?;
+
+        let offset = partition.offset + (Self::SECTOR_SIZE * index) as u32;
+        let mut data = [0; OtaSelectEntry::SIZE];
+        entry.to_bytes(&mut data);
+
+        storage
+            .erase(offset, offset + Self::SECTOR_SIZE as u32)
+            .map_err(NorFlashOpError::StorageError)?;
+        storage
+            .write(offset, &data)
+            .map_err(NorFlashOpError::StorageError)
+    }
+
+    /// Select OTA application slot to boot in otadata partition
+    ///
+    /// See [`OtaData::set_boot_slot`] for details. Returns updated OTA data.
+    #[cfg(feature = "embedded-storage")]
+    pub fn set_boot_slot_nor_flash<S>(
+        storage: &mut S,
+        partition: &PartitionEntry,
+        slot: u8,
+        ota_count: usize,
+        state: OtaImageState,
+    ) -> Result<Self, NorFlashOpError<S>>
+    where
+        S: NorFlash,
+    {
+        let mut data = Self::read_nor_flash(storage, partition)?;
+        let index = data.set_boot_slot(slot, ota_count, state)?;
+        data.write_nor_flash(storage, partition, index)?;
+        Ok(data)
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+fn check_partition(partition: &PartitionEntry) -> Result<(), PartitionError> {
+    if partition.type_ != PartitionType::Data(DataPartitionType::Ota) {
+        return Err(PartitionError::InvalidPartition);
+    }
+    if partition.size < OtaData::SIZE {
+        return Err(PartitionError::NotEnoughData);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    fn entry(seq: u32) -> OtaSelectEntry {
+        let mut entry = OtaSelectEntry {
+            seq,
+            ..Default::default()
+        };
+        entry.crc = entry.calc_crc();
+        entry
+    }
+
+    #[test]
+    fn select_entry() {
+        let mut data = [0xff; OtaSelectEntry::SIZE];
+        data[..4].copy_from_slice(&[0x01, 0x00, 0x00, 0x00]);
+        data[28..].copy_from_slice(&[0x9a, 0x98, 0x43, 0x47]);
+
+        let entry = OtaSelectEntry::from_bytes(&data);
+        assert_eq!(entry.seq, 1);
+        assert_eq!(entry.state, OtaImageState::Undefined);
+        assert_eq!(entry.crc, 0x4743989a);
+        assert!(entry.is_valid());
+
+        let mut copy = [0; OtaSelectEntry::SIZE];
+        entry.to_bytes(&mut copy);
+        assert_eq!(copy, data);
+
+        assert!(!OtaSelectEntry::default().is_valid());
+        assert!(!OtaSelectEntry {
+            state: OtaImageState::Aborted,
+            ..entry
+        }
+        .is_valid());
+        assert!(!OtaSelectEntry { crc: 0, ..entry }.is_valid());
+    }
+
+    #[test]
+    fn boot_slot() {
+        let mut data = OtaData::default();
+        assert_eq!(data.active(), None);
+        assert_eq!(data.boot_slot(2), None);
+
+        data.entries = [entry(5), entry(4)];
+        assert_eq!(data.active(), Some(0));
+        assert_eq!(data.boot_slot(2), Some(AppPartitionType::Ota(0)));
+        assert_eq!(data.boot_slot(3), Some(AppPartitionType::Ota(1)));
+
+        data.entries[1] = entry(6);
+        assert_eq!(data.active(), Some(1));
+        assert_eq!(data.boot_slot(2), Some(AppPartitionType::Ota(1)));
+
+        data.entries[1].crc ^= 1;
+        assert_eq!(data.active(), Some(0));
+
+        let partitions = include_bytes!("../tests/partitions-ota.bin")
+            .chunks_exact(PartitionEntry::SIZE)
+            .map_while(|part| PartitionEntry::try_from(part).ok());
+        assert_eq!(OtaData::count_slots(partitions), 2);
+    }
+
+    #[test]
+    fn set_boot_slot() {
+        let mut data = OtaData::default();
+        let state = OtaImageState::Undefined;
+
+        assert_eq!(data.set_boot_slot(1, 2, state), Ok(0));
+        assert_eq!(data.entries[0].seq, 2);
+        assert_eq!(data.boot_slot(2), Some(AppPartitionType::Ota(1)));
+
+        assert_eq!(data.set_boot_slot(0, 2, state), Ok(1));
+        assert_eq!(data.entries[1].seq, 3);
+        assert_eq!(data.boot_slot(2), Some(AppPartitionType::Ota(0)));
+
+        assert_eq!(data.set_boot_slot(1, 2, OtaImageState::New), Ok(0));
+        assert_eq!(data.entries[0].seq, 4);
+        assert_eq!(data.entries[0].state, OtaImageState::New);
+        assert_eq!(data.boot_slot(2), Some(AppPartitionType::Ota(1)));
+
+        assert_eq!(data.set_boot_slot(1, 2, state), Ok(1));
+        assert_eq!(data.entries[1].seq, 4);
+
+        let mut data = OtaData::default();
+        data.entries[0] = entry(5);
+        assert_eq!(data.set_boot_slot(0, 3, state), Ok(1));
+        assert_eq!(data.entries[1].seq, 7);
+        assert_eq!(data.boot_slot(3), Some(AppPartitionType::Ota(0)));
+
+        assert_eq!(
+            data.set_boot_slot(2, 2, state),
+            Err(PartitionError::InvalidOtaNumber(2))
+        );
+
+        let mut data = OtaData::default();
+        data.entries[0] = entry(u32::MAX - 1);
+        assert_eq!(
+            data.set_boot_slot(0, 2, state),
+            Err(PartitionError::SequenceOverflow)
+        );
+        assert_eq!(
+            data.set_boot_slot(0, 3, state),
+            Err(PartitionError::SequenceOverflow)
+        );
+        assert_eq!(data.set_boot_slot(1, 2, state), Ok(1));
+        assert_eq!(data.entries[1].seq, u32::MAX - 1);
+    }
+
+    #[cfg(feature = "embedded-storage")]
+    #[test]
+    fn nor_flash() {
+        let mut flash = NorFlashSim::<_>::new([0x00; 0xd000 + OtaData::SIZE]);
+        let partition =
+            PartitionEntry::new(DataPartitionType::Ota, 0xd000, 0x2000, "otadata", false).unwrap();
+
+        let data = OtaData::read_nor_flash(&mut flash, &partition).unwrap();
+        assert_eq!(data.active(), None);
+
+        let data = OtaData::set_boot_slot_nor_flash(
+            &mut flash,
+            &partition,
+            0,
+            2,
+            OtaImageState::Undefined,
+        )
+        .unwrap();
+        assert_eq!(data.boot_slot(2), Some(AppPartitionType::Ota(0)));
+        let otadata = &flash.data()[0xd000..];
+        assert_eq!(&otadata[..4], &[0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(&otadata[28..32], &[0x9a, 0x98, 0x43, 0x47]);
+        assert!(otadata[32..OtaData::SECTOR_SIZE].iter().all(|b| *b == 0xff));
+        assert!(otadata[OtaData::SECTOR_SIZE..].iter().all(|b| *b == 0x00));
+
+        let data = OtaData::set_boot_slot_nor_flash(
+            &mut flash,
+            &partition,
+            1,
+            2,
+            OtaImageState::Undefined,
+        )
+        .unwrap();
+        assert_eq!(
+            OtaData::read_nor_flash(&mut flash, &partition).unwrap(),
+            data
+        );
+        assert_eq!(data.boot_slot(2), Some(AppPartitionType::Ota(1)));
+        assert_eq!(data.entries[1].seq, 2);
+
+        let nvs =
+            PartitionEntry::new(DataPartitionType::Nvs, 0xd000, 0x2000, "nvs", false).unwrap();
+        assert!(matches!(
+            OtaData::read_nor_flash(&mut flash, &nvs),
+            Err(NorFlashOpError::PartitionError(
+                PartitionError::InvalidPartition
+            ))
+        ));
+    }
+}
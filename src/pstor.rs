@@ -0,0 +1,261 @@
+use crate::{NorFlashOpError, PartitionEntry, PartitionError, StorageOpError};
+use core::ops::Range;
+use embedded_storage::{
+    nor_flash::{ErrorType, MultiwriteNorFlash, NorFlash, ReadNorFlash},
+    ReadStorage, Storage,
+};
+
+/// Storage restricted to single partition
+///
+/// Addresses are relative to the start of partition and accesses out of
+/// partition bounds fail with [`PartitionError::OutOfBounds`].
+/// Writing and erasing of read-only partitions fail with [`PartitionError::ReadOnly`].
+/// Erasing fails with [`PartitionError::InvalidAlignment`] when partition offset or size
+/// isn't a multiple of erase size of underlying storage.
+///
+/// Implements NOR flash (blocking and async) and storage traits when underlying storage does.
+#[derive(Clone, Debug)]
+pub struct PartitionStorage<S> {
+    storage: S,
+    partition: PartitionEntry,
+}
+
+impl<S> PartitionStorage<S> {
+    /// Wrap storage to access specified partition
+    pub fn new(storage: S, partition: PartitionEntry) -> Self {
+        Self { storage, partition }
+    }
+
+    /// Get partition entry
+    pub fn partition(&self) -> &PartitionEntry {
+        &self.partition
+    }
+
+    /// Get underlying storage
+    pub fn storage(&mut self) -> &mut S {
+        &mut self.storage
+    }
+
+    /// Unwrap underlying storage
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    /// Get absolute address for range of partition
+    fn addr(&self, offset: u32, length: usize) -> Result<u32, PartitionError> {
+        (offset as usize)
+            .checked_add(length)
+            .filter(|end| *end <= self.partition.size)
+            .map(|_| self.partition.offset + offset)
+            .ok_or(PartitionError::OutOfBounds)
+    }
+
+    /// Get absolute address for writable range of partition
+    fn write_addr(&self, offset: u32, length: usize) -> Result<u32, PartitionError> {
+        if self.partition.flags.readonly() {
+            return Err(PartitionError::ReadOnly);
+        }
+        self.addr(offset, length)
+    }
+
+    /// Get absolute address range for erasable range of partition
+    ///
+    /// The partition should consist of whole erase blocks otherwise aligned range
+    /// of partition maps to misaligned range of storage.
+    fn erase_addr(
+        &self,
+        from: u32,
+        to: u32,
+        erase_size: usize,
+    ) -> Result<Range<u32>, PartitionError> {
+        let length = to.checked_sub(from).ok_or(PartitionError::OutOfBounds)?;
+        let addr = self.write_addr(from, length as usize)?;
+        if self.partition.offset as usize % erase_size != 0 || self.partition.size % erase_size != 0
+        {
+            return Err(PartitionError::InvalidAlignment);
+        }
+        Ok(addr..addr + length)
+    }
+}
+
+impl<S: ErrorType> ErrorType for PartitionStorage<S> {
+    type Error = NorFlashOpError<S>;
+}
+
+impl<S: ReadNorFlash> ReadNorFlash for PartitionStorage<S> {
+    const READ_SIZE: usize = S::READ_SIZE;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let addr = self.addr(offset, bytes.len())?;
+        self.storage
+            .read(addr, bytes)
+            .map_err(NorFlashOpError::StorageError)
+    }
+
+    fn capacity(&self) -> usize {
+        self.partition.size
+    }
+}
+
+impl<S: NorFlash> NorFlash for PartitionStorage<S> {
+    const WRITE_SIZE: usize = S::WRITE_SIZE;
+    const ERASE_SIZE: usize = S::ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        let range = self.erase_addr(from, to, S::ERASE_SIZE)?;
+        self.storage
+            .erase(range.start, range.end)
+            .map_err(NorFlashOpError::StorageError)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let addr = self.write_addr(offset, bytes.len())?;
+        self.storage
+            .write(addr, bytes)
+            .map_err(NorFlashOpError::StorageError)
+    }
+}
+
+impl<S: MultiwriteNorFlash> MultiwriteNorFlash for PartitionStorage<S> {}
+
+#[cfg(feature = "embedded-storage-async")]
+impl<S: embedded_storage_async::nor_flash::ReadNorFlash>
+    embedded_storage_async::nor_flash::ReadNorFlash for PartitionStorage<S>
+{
+    const READ_SIZE: usize = S::READ_SIZE;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let addr = self.addr(offset, bytes.len())?;
+        self.storage
+            .read(addr, bytes)
+            .await
+            .map_err(NorFlashOpError::StorageError)
+    }
+
+    fn capacity(&self) -> usize {
+        self.partition.size
+    }
+}
+
+#[cfg(feature = "embedded-storage-async")]
+impl<S: embedded_storage_async::nor_flash::NorFlash> embedded_storage_async::nor_flash::NorFlash
+    for PartitionStorage<S>
+{
+    const WRITE_SIZE: usize = S::WRITE_SIZE;
+    const ERASE_SIZE: usize = S::ERASE_SIZE;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        let range = self.erase_addr(from, to, S::ERASE_SIZE)?;
+        self.storage
+            .erase(range.start, range.end)
+            .await
+            .map_err(NorFlashOpError::StorageError)
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let addr = self.write_addr(offset, bytes.len())?;
+        self.storage
+            .write(addr, bytes)
+            .await
+            .map_err(NorFlashOpError::StorageError)
+    }
+}
+
+#[cfg(feature = "embedded-storage-async")]
+impl<S: embedded_storage_async::nor_flash::MultiwriteNorFlash>
+    embedded_storage_async::nor_flash::MultiwriteNorFlash for PartitionStorage<S>
+{
+}
+
+impl<S: ReadStorage> ReadStorage for PartitionStorage<S> {
+    type Error = StorageOpError<S>;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let addr = self.addr(offset, bytes.len())?;
+        self.storage
+            .read(addr, bytes)
+            .map_err(StorageOpError::StorageError)
+    }
+
+    fn capacity(&self) -> usize {
+        self.partition.size
+    }
+}
+
+impl<S: Storage> Storage for PartitionStorage<S> {
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let addr = self.write_addr(offset, bytes.len())?;
+        self.storage
+            .write(addr, bytes)
+            .map_err(StorageOpError::StorageError)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash};
+
+    #[test]
+    fn nor_flash() {
+        let mut flash = NorFlashSim::<_>::new([0; 0x3000]);
+        let partition =
+            PartitionEntry::new(DataPartitionType::Nvs, 0x1000, 0x1000, "nvs", false).unwrap();
+        let mut storage = PartitionStorage::new(&mut flash, partition.clone());
+        assert_eq!(ReadNorFlash::capacity(&storage), 0x1000);
+
+        storage.erase(0, 0x1000).unwrap();
+        NorFlash::write(&mut storage, 0xffc, &[1, 2, 3, 4]).unwrap();
+
+        let mut data = [0; 4];
+        ReadNorFlash::read(&mut storage, 0xffc, &mut data).unwrap();
+        assert_eq!(data, [1, 2, 3, 4]);
+
+        assert_eq!(
+            ReadNorFlash::read(&mut storage, 0xffd, &mut data),
+            Err(NorFlashOpError::PartitionError(PartitionError::OutOfBounds))
+        );
+        assert_eq!(
+            NorFlash::write(&mut storage, u32::MAX, &data),
+            Err(NorFlashOpError::PartitionError(PartitionError::OutOfBounds))
+        );
+        assert_eq!(
+            storage.erase(0x1000, 0x2000).unwrap_err().kind(),
+            NorFlashErrorKind::OutOfBounds
+        );
+        assert_eq!(
+            storage.erase(0x800, 0x1000).unwrap_err().kind(),
+            NorFlashErrorKind::NotAligned
+        );
+
+        assert_eq!(flash.data()[..0x1000], [0; 0x1000]);
+        assert_eq!(flash.data()[0x1000..0x1ffc], [0xff; 0xffc]);
+        assert_eq!(flash.data()[0x1ffc..0x2000], [1, 2, 3, 4]);
+        assert_eq!(flash.data()[0x2000..], [0; 0x1000]);
+
+        let mut partition = partition;
+        partition.flags.set_readonly(true);
+        let mut storage = PartitionStorage::new(&mut flash, partition);
+        ReadNorFlash::read(&mut storage, 0xffc, &mut data).unwrap();
+        assert_eq!(
+            NorFlash::write(&mut storage, 0, &data),
+            Err(NorFlashOpError::PartitionError(PartitionError::ReadOnly))
+        );
+        assert_eq!(
+            storage.erase(0, 0x1000),
+            Err(NorFlashOpError::PartitionError(PartitionError::ReadOnly))
+        );
+
+        // Partition which doesn't consist of whole erase blocks
+        let partition =
+            PartitionEntry::new(DataPartitionType::Nvs, 0x1000, 0x1800, "nvs", false).unwrap();
+        let mut storage = PartitionStorage::new(&mut flash, partition);
+        ReadNorFlash::read(&mut storage, 0x17fc, &mut data).unwrap();
+        assert_eq!(
+            storage.erase(0, 0x1000),
+            Err(NorFlashOpError::PartitionError(
+                PartitionError::InvalidAlignment
+            ))
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::PartitionViolation;
 use core::fmt;
 
 /// Partition manipulation error
@@ -18,6 +19,9 @@ pub enum PartitionError {
     /// OTA partition number is not a valid
     InvalidOtaNumber(u8),
 
+    /// OTA sequence number cannot be incremented anymore
+    SequenceOverflow,
+
     /// String data is not a valid
     InvalidString,
 
@@ -27,11 +31,43 @@ pub enum PartitionError {
     /// MD5 checksum is not a valid
     InvalidMd5,
 
+    /// Image checksum is not a valid
+    InvalidChecksum,
+
+    /// SHA-256 digest is not a valid
+    InvalidSha256,
+
     /// Not enough data
     NotEnoughData,
 
     /// Too many data
     TooManyData,
+
+    /// Not enough free space in partition
+    NotEnoughSpace,
+
+    /// Partitions layout is not a valid
+    InvalidLayout(PartitionViolation),
+
+    /// Partition is not a suitable for operation
+    InvalidPartition,
+
+    /// Access is out of partition bounds
+    OutOfBounds,
+
+    /// Partition is read-only
+    ReadOnly,
+
+    /// Written table differs from intended at entry with specified index
+    ///
+    /// The index of MD5 entry is the number of partitions.
+    VerifyFailed(usize),
+}
+
+impl From<PartitionViolation> for PartitionError {
+    fn from(violation: PartitionViolation) -> Self {
+        Self::InvalidLayout(violation)
+    }
 }
 
 impl fmt::Display for PartitionError {
@@ -55,11 +91,26 @@ impl fmt::Display for PartitionError {
                 "Invalid OTA: #".fmt(f)?;
                 no.fmt(f)
             }
+            SequenceOverflow => "OTA sequence number overflow".fmt(f),
             InvalidString => "Invalid string".fmt(f),
             InvalidAlignment => "Invalid alignment".fmt(f),
             InvalidMd5 => "Invalid MD5".fmt(f),
+            InvalidChecksum => "Invalid checksum".fmt(f),
+            InvalidSha256 => "Invalid SHA-256".fmt(f),
             NotEnoughData => "Not enough data".fmt(f),
             TooManyData => "Too many data".fmt(f),
+            NotEnoughSpace => "Not enough space".fmt(f),
+            InvalidLayout(violation) => {
+                "Invalid layout: ".fmt(f)?;
+                violation.fmt(f)
+            }
+            InvalidPartition => "Invalid partition".fmt(f),
+            OutOfBounds => "Out of bounds".fmt(f),
+            ReadOnly => "Read-only partition".fmt(f),
+            VerifyFailed(index) => {
+                "Verification failed at entry #".fmt(f)?;
+                index.fmt(f)
+            }
         }
     }
 }
@@ -0,0 +1,359 @@
+use crate::{
+    csv::{parse_subtype, parse_type},
+    AppPartitionType, DataPartitionType, PartitionEntry, PartitionFlags, PartitionMd5,
+    PartitionType,
+};
+use core::fmt;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// String deserialized into fixed buffer
+struct Text<const N: usize> {
+    data: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Default for Text<N> {
+    fn default() -> Self {
+        Self {
+            data: [0; N],
+            len: 0,
+        }
+    }
+}
+
+impl<const N: usize> Text<N> {
+    fn as_str(&self) -> &str {
+        // utf8 data copied from str
+        unsafe { core::str::from_utf8_unchecked(&self.data[..self.len]) }
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for Text<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor<const N: usize>;
+
+        impl<const N: usize> de::Visitor<'_> for Visitor<N> {
+            type Value = Text<N>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a string up to {N} bytes")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                if value.len() > N {
+                    return Err(E::invalid_length(value.len(), &self));
+                }
+                let mut text = Text::default();
+                text.data[..value.len()].copy_from_slice(value.as_bytes());
+                text.len = value.len();
+                Ok(text)
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+/// Maximum length of type or subtype name
+const TYPE_NAME_LEN: usize = 16;
+
+/// Type name like in CSV
+struct TypeName(PartitionType);
+
+impl fmt::Display for TypeName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            PartitionType::User(type_, _) => write!(f, "{type_:#04x}"),
+            PartitionType::Any => "0xff".fmt(f),
+            type_ => type_.type_name().unwrap_or_default().fmt(f),
+        }
+    }
+}
+
+/// Subtype name like in CSV
+struct SubtypeName(PartitionType);
+
+impl fmt::Display for SubtypeName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            PartitionType::App(subtype) => subtype.fmt(f),
+            PartitionType::Data(subtype) => subtype.fmt(f),
+            PartitionType::Bootloader(subtype) => subtype.fmt(f),
+            PartitionType::PartitionTable(subtype) => subtype.fmt(f),
+            PartitionType::User(_, subtype) => write!(f, "{subtype:#04x}"),
+            PartitionType::Any => "0x00".fmt(f),
+        }
+    }
+}
+
+fn parse_partition_type<E: de::Error>(type_: &str, subtype: &str) -> Result<PartitionType, E> {
+    let raw_type = parse_type(type_).map_err(E::custom)?;
+    let raw_subtype = parse_subtype(raw_type, subtype).map_err(E::custom)?;
+    PartitionType::from_bytes_lenient(&[raw_type, raw_subtype]).map_err(E::custom)
+}
+
+/// Serialized as `{"type": "app", "subtype": "ota_0"}`
+impl Serialize for PartitionType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename = "PartitionType")]
+        struct Repr {
+            #[serde(rename = "type", serialize_with = "serialize_display")]
+            type_: TypeName,
+            #[serde(serialize_with = "serialize_display")]
+            subtype: SubtypeName,
+        }
+
+        Repr {
+            type_: TypeName(*self),
+            subtype: SubtypeName(*self),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PartitionType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "PartitionType")]
+        struct Repr {
+            #[serde(rename = "type")]
+            type_: Text<TYPE_NAME_LEN>,
+            #[serde(default)]
+            subtype: Text<TYPE_NAME_LEN>,
+        }
+
+        let repr = Repr::deserialize(deserializer)?;
+        parse_partition_type(repr.type_.as_str(), repr.subtype.as_str())
+    }
+}
+
+/// Serialized as name like `ota_0`
+impl Serialize for AppPartitionType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for AppPartitionType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = Text::<TYPE_NAME_LEN>::deserialize(deserializer)?;
+        parse_subtype(0x00, name.as_str())
+            .map(Self::from_raw)
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Serialized as name like `nvs`
+impl Serialize for DataPartitionType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DataPartitionType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = Text::<TYPE_NAME_LEN>::deserialize(deserializer)?;
+        parse_subtype(0x01, name.as_str())
+            .map(Self::from_raw)
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Serialized like CSV row with offset and size in bytes
+///
+/// Only known flags are represented.
+impl Serialize for PartitionEntry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename = "PartitionEntry")]
+        struct Repr<'a> {
+            name: &'a str,
+            #[serde(rename = "type", serialize_with = "serialize_display")]
+            type_: TypeName,
+            #[serde(serialize_with = "serialize_display")]
+            subtype: SubtypeName,
+            offset: u32,
+            size: usize,
+            encrypted: bool,
+            readonly: bool,
+        }
+
+        Repr {
+            name: self.name(),
+            type_: TypeName(self.type_),
+            subtype: SubtypeName(self.type_),
+            offset: self.offset,
+            size: self.size,
+            encrypted: self.flags.encrypted(),
+            readonly: self.flags.readonly(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PartitionEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "PartitionEntry")]
+        struct Repr {
+            name: Text<{ PartitionEntry::MAX_NAME_LEN }>,
+            #[serde(rename = "type")]
+            type_: Text<TYPE_NAME_LEN>,
+            #[serde(default)]
+            subtype: Text<TYPE_NAME_LEN>,
+            offset: u32,
+            size: usize,
+            #[serde(default)]
+            encrypted: bool,
+            #[serde(default)]
+            readonly: bool,
+        }
+
+        let repr = Repr::deserialize(deserializer)?;
+        let type_ = parse_partition_type(repr.type_.as_str(), repr.subtype.as_str())?;
+        let mut flags = PartitionFlags::NONE;
+        flags.set_encrypted(repr.encrypted);
+        flags.set_readonly(repr.readonly);
+
+        PartitionEntry::new(type_, repr.offset, repr.size, repr.name.as_str(), flags)
+            .map_err(de::Error::custom)
+    }
+}
+
+/// MD5 checksum in hex
+struct Md5Hex<'a>(&'a PartitionMd5);
+
+impl fmt::Display for Md5Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0.data {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Serialized as hex string
+impl Serialize for PartitionMd5 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&Md5Hex(self))
+    }
+}
+
+impl<'de> Deserialize<'de> for PartitionMd5 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = Text::<32>::deserialize(deserializer)?;
+        let hex = hex.as_str();
+        let mut md5 = Self::default();
+        if hex.len() != md5.data.len() * 2 {
+            return Err(de::Error::invalid_length(hex.len(), &"32 hex digits"));
+        }
+        for (byte, digits) in md5.data.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+            // digits are ASCII when parsed successfully
+            *byte = core::str::from_utf8(digits)
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(hex), &"hex digits"))?;
+        }
+        Ok(md5)
+    }
+}
+
+fn serialize_display<S: Serializer>(
+    value: &impl fmt::Display,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn partition_types() {
+        for (type_, json) in [
+            (
+                PartitionType::App(AppPartitionType::Ota(3)),
+                r#"{"type":"app","subtype":"ota_3"}"#,
+            ),
+            (
+                PartitionType::Data(DataPartitionType::NvsKeys),
+                r#"{"type":"data","subtype":"nvs_keys"}"#,
+            ),
+            (
+                PartitionType::Data(DataPartitionType::Other(0x40)),
+                r#"{"type":"data","subtype":"0x40"}"#,
+            ),
+            (
+                PartitionType::Bootloader(BootloaderPartitionType::Recovery),
+                r#"{"type":"bootloader","subtype":"recovery"}"#,
+            ),
+            (
+                PartitionType::User(0x40, 0x07),
+                r#"{"type":"0x40","subtype":"0x07"}"#,
+            ),
+        ] {
+            assert_eq!(serde_json::to_string(&type_).unwrap(), json);
+            assert_eq!(serde_json::from_str::<PartitionType>(json).unwrap(), type_);
+        }
+
+        assert_eq!(
+            serde_json::from_str::<PartitionType>(r#"{"type":"Data"}"#).unwrap(),
+            PartitionType::Data(DataPartitionType::Undefined)
+        );
+        assert!(serde_json::from_str::<PartitionType>(r#"{"type":"foo"}"#).is_err());
+
+        assert_eq!(
+            serde_json::to_string(&AppPartitionType::Factory).unwrap(),
+            r#""factory""#
+        );
+        assert_eq!(
+            serde_json::from_str::<AppPartitionType>(r#""ota_15""#).unwrap(),
+            AppPartitionType::Ota(15)
+        );
+        assert_eq!(
+            serde_json::to_string(&DataPartitionType::SpiFfs).unwrap(),
+            r#""spiffs""#
+        );
+        assert_eq!(
+            serde_json::from_str::<DataPartitionType>(r#""coredump""#).unwrap(),
+            DataPartitionType::CoreDump
+        );
+    }
+
+    #[test]
+    fn partition_entry() {
+        let mut entry = PartitionEntry::new(
+            AppPartitionType::Ota(0),
+            0x110000,
+            0x100000,
+            "ota_0",
+            PartitionFlags::ENCRYPTED,
+        )
+        .unwrap();
+        let json = r#"{"name":"ota_0","type":"app","subtype":"ota_0","offset":1114112,"size":1048576,"encrypted":true,"readonly":false}"#;
+        assert_eq!(serde_json::to_string(&entry).unwrap(), json);
+        assert_eq!(serde_json::from_str::<PartitionEntry>(json).unwrap(), entry);
+
+        entry.type_ = DataPartitionType::Nvs.into();
+        entry.flags = PartitionFlags::READONLY;
+        assert_eq!(
+            serde_json::from_str::<PartitionEntry>(
+                r#"{"name":"ota_0","type":"data","subtype":"nvs","offset":1114112,"size":1048576,"readonly":true}"#
+            )
+            .unwrap(),
+            entry
+        );
+        assert!(serde_json::from_str::<PartitionEntry>(
+            r#"{"name":"very_long_partition_name","type":"data","subtype":"nvs","offset":0,"size":0}"#
+        )
+        .is_err());
+
+        let md5 = PartitionMd5::from(core::array::from_fn(|index| index as u8 * 0x11));
+        let json = r#""00112233445566778899aabbccddeeff""#;
+        assert_eq!(serde_json::to_string(&md5).unwrap(), json);
+        assert_eq!(serde_json::from_str::<PartitionMd5>(json).unwrap(), md5);
+        assert!(serde_json::from_str::<PartitionMd5>(r#""0011""#).is_err());
+    }
+}
@@ -0,0 +1,320 @@
+use embedded_storage::nor_flash::{
+    ErrorType, MultiwriteNorFlash, NorFlash, NorFlashErrorKind, ReadNorFlash,
+};
+
+/// In-memory NOR flash simulator
+///
+/// Programming can only clear bits, erasing sets whole blocks to `0xff`.
+/// Accesses should be aligned to corresponding sizes and fit into memory.
+///
+/// Faults can be injected to test robustness of code which uses flash:
+/// - [`NorFlashSim::inject_error`] fails the next operation without any changes
+/// - [`NorFlashSim::inject_stuck_byte`] makes byte ignore programming and erasing silently
+/// - [`NorFlashSim::power_cut_after`] stops programming and erasing after specified
+///   number of bytes, the operations fail until [`NorFlashSim::power_on`] is called
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NorFlashSim<
+    B,
+    const READ_SIZE: usize = 1,
+    const WRITE_SIZE: usize = 1,
+    const ERASE_SIZE: usize = 4096,
+> {
+    data: B,
+    written: usize,
+    budget: Option<usize>,
+    powered: bool,
+    error: Option<NorFlashErrorKind>,
+    stuck: Option<usize>,
+}
+
+impl<B, const READ_SIZE: usize, const WRITE_SIZE: usize, const ERASE_SIZE: usize>
+    NorFlashSim<B, READ_SIZE, WRITE_SIZE, ERASE_SIZE>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    /// Create simulator using specified memory as flash contents
+    pub fn new(data: B) -> Self {
+        Self {
+            data,
+            written: 0,
+            budget: None,
+            powered: true,
+            error: None,
+            stuck: None,
+        }
+    }
+
+    /// Get flash contents
+    pub fn data(&self) -> &[u8] {
+        self.data.as_ref()
+    }
+
+    /// Get mutable flash contents
+    ///
+    /// Useful to corrupt data intentionally.
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        self.data.as_mut()
+    }
+
+    /// Unwrap flash contents
+    pub fn into_inner(self) -> B {
+        self.data
+    }
+
+    /// Get number of bytes programmed or erased so far
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Cut power after specified number of bytes is programmed or erased
+    ///
+    /// The operation which exceeds the limit is applied partially and fails.
+    pub fn power_cut_after(&mut self, bytes: usize) {
+        self.budget = Some(bytes);
+    }
+
+    /// Power is on
+    pub fn is_powered(&self) -> bool {
+        self.powered
+    }
+
+    /// Restore power after cut and cancel pending power cut
+    pub fn power_on(&mut self) {
+        self.budget = None;
+        self.powered = true;
+    }
+
+    /// Fail the next operation with specified error
+    pub fn inject_error(&mut self, error: NorFlashErrorKind) {
+        self.error = Some(error);
+    }
+
+    /// Make byte at specified offset keep its value when programming and erasing
+    ///
+    /// Simulates worn out cell which can be detected only by reading data back.
+    pub fn inject_stuck_byte(&mut self, offset: usize) {
+        self.stuck = Some(offset);
+    }
+
+    fn check(
+        &mut self,
+        offset: u32,
+        length: usize,
+        align: usize,
+    ) -> Result<usize, NorFlashErrorKind> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        if !self.powered {
+            return Err(NorFlashErrorKind::Other);
+        }
+
+        let offset = offset as usize;
+        if offset % align != 0 || length % align != 0 {
+            return Err(NorFlashErrorKind::NotAligned);
+        }
+        if offset
+            .checked_add(length)
+            .map_or(true, |end| end > self.data.as_ref().len())
+        {
+            return Err(NorFlashErrorKind::OutOfBounds);
+        }
+
+        Ok(offset)
+    }
+
+    /// Apply modification to range of bytes taking power cut into account
+    fn modify(
+        &mut self,
+        offset: usize,
+        length: usize,
+        op: impl Fn(&mut u8, usize),
+    ) -> Result<(), NorFlashErrorKind> {
+        let allowed = self.budget.map_or(length, |budget| budget.min(length));
+        let stuck = self.stuck;
+
+        for (index, byte) in self.data.as_mut()[offset..][..allowed]
+            .iter_mut()
+            .enumerate()
+        {
+            if stuck != Some(offset + index) {
+                op(byte, index);
+            }
+        }
+
+        self.written += allowed;
+
+        if let Some(budget) = &mut self.budget {
+            *budget -= allowed;
+            if allowed < length {
+                self.powered = false;
+                return Err(NorFlashErrorKind::Other);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<B, const READ_SIZE: usize, const WRITE_SIZE: usize, const ERASE_SIZE: usize> ErrorType
+    for NorFlashSim<B, READ_SIZE, WRITE_SIZE, ERASE_SIZE>
+{
+    type Error = NorFlashErrorKind;
+}
+
+impl<B, const READ_SIZE: usize, const WRITE_SIZE: usize, const ERASE_SIZE: usize> ReadNorFlash
+    for NorFlashSim<B, READ_SIZE, WRITE_SIZE, ERASE_SIZE>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    const READ_SIZE: usize = READ_SIZE;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = self.check(offset, bytes.len(), READ_SIZE)?;
+        bytes.copy_from_slice(&self.data.as_ref()[offset..][..bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.as_ref().len()
+    }
+}
+
+impl<B, const READ_SIZE: usize, const WRITE_SIZE: usize, const ERASE_SIZE: usize> NorFlash
+    for NorFlashSim<B, READ_SIZE, WRITE_SIZE, ERASE_SIZE>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    const WRITE_SIZE: usize = WRITE_SIZE;
+    const ERASE_SIZE: usize = ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        let length = to.checked_sub(from).ok_or(NorFlashErrorKind::OutOfBounds)? as usize;
+        let offset = self.check(from, length, ERASE_SIZE)?;
+        self.modify(offset, length, |byte, _| *byte = 0xff)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let offset = self.check(offset, bytes.len(), WRITE_SIZE)?;
+        self.modify(offset, bytes.len(), |byte, index| *byte &= bytes[index])
+    }
+}
+
+impl<B, const READ_SIZE: usize, const WRITE_SIZE: usize, const ERASE_SIZE: usize> MultiwriteNorFlash
+    for NorFlashSim<B, READ_SIZE, WRITE_SIZE, ERASE_SIZE>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+}
+
+#[cfg(feature = "embedded-storage-async")]
+impl<B, const READ_SIZE: usize, const WRITE_SIZE: usize, const ERASE_SIZE: usize>
+    embedded_storage_async::nor_flash::ReadNorFlash
+    for NorFlashSim<B, READ_SIZE, WRITE_SIZE, ERASE_SIZE>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    const READ_SIZE: usize = READ_SIZE;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        ReadNorFlash::read(self, offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        ReadNorFlash::capacity(self)
+    }
+}
+
+#[cfg(feature = "embedded-storage-async")]
+impl<B, const READ_SIZE: usize, const WRITE_SIZE: usize, const ERASE_SIZE: usize>
+    embedded_storage_async::nor_flash::NorFlash
+    for NorFlashSim<B, READ_SIZE, WRITE_SIZE, ERASE_SIZE>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    const WRITE_SIZE: usize = WRITE_SIZE;
+    const ERASE_SIZE: usize = ERASE_SIZE;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        NorFlash::erase(self, from, to)
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        NorFlash::write(self, offset, bytes)
+    }
+}
+
+#[cfg(feature = "embedded-storage-async")]
+impl<B, const READ_SIZE: usize, const WRITE_SIZE: usize, const ERASE_SIZE: usize>
+    embedded_storage_async::nor_flash::MultiwriteNorFlash
+    for NorFlashSim<B, READ_SIZE, WRITE_SIZE, ERASE_SIZE>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use embedded_storage::nor_flash::{NorFlash, NorFlashErrorKind, ReadNorFlash};
+
+    #[test]
+    fn nor_flash_sim() {
+        let mut flash = NorFlashSim::<_, 4, 4, 0x100>::new([0u8; 0x400]);
+        assert_eq!(flash.capacity(), 0x400);
+
+        assert_eq!(flash.erase(0x80, 0x100), Err(NorFlashErrorKind::NotAligned));
+        assert_eq!(
+            flash.erase(0x300, 0x500),
+            Err(NorFlashErrorKind::OutOfBounds)
+        );
+        flash.erase(0x100, 0x200).unwrap();
+        assert_eq!(flash.data()[0xff], 0);
+        assert_eq!(flash.data()[0x100..0x200], [0xff; 0x100]);
+        assert_eq!(flash.data()[0x200], 0);
+
+        assert_eq!(
+            flash.write(0x102, &[0; 4]),
+            Err(NorFlashErrorKind::NotAligned)
+        );
+        assert_eq!(
+            flash.write(0x100, &[0; 3]),
+            Err(NorFlashErrorKind::NotAligned)
+        );
+        flash.write(0x100, &[0xf0, 0x0f, 0x55, 0xff]).unwrap();
+        flash.write(0x100, &[0x3c, 0x3c, 0xff, 0x00]).unwrap();
+
+        let mut data = [0; 4];
+        assert_eq!(
+            flash.read(0x101, &mut data),
+            Err(NorFlashErrorKind::NotAligned)
+        );
+        flash.read(0x100, &mut data).unwrap();
+        assert_eq!(data, [0x30, 0x0c, 0x55, 0x00]);
+        assert_eq!(flash.written(), 0x108);
+
+        flash.inject_error(NorFlashErrorKind::Other);
+        assert_eq!(flash.read(0x100, &mut data), Err(NorFlashErrorKind::Other));
+        flash.read(0x100, &mut data).unwrap();
+
+        flash.power_cut_after(0x106);
+        flash.write(0x104, &[0; 4]).unwrap();
+        assert_eq!(flash.erase(0x100, 0x300), Err(NorFlashErrorKind::Other));
+        assert!(!flash.is_powered());
+        assert_eq!(flash.read(0x100, &mut data), Err(NorFlashErrorKind::Other));
+        assert_eq!(flash.written(), 0x20e);
+
+        flash.power_on();
+        assert_eq!(flash.data()[0x100..0x202], [0xff; 0x102]);
+        flash.read(0x200, &mut data).unwrap();
+        assert_eq!(data, [0xff, 0xff, 0, 0]);
+
+        flash.inject_stuck_byte(0x202);
+        flash.erase(0x200, 0x300).unwrap();
+        flash.read(0x200, &mut data).unwrap();
+        assert_eq!(data, [0xff, 0xff, 0, 0xff]);
+        flash.write(0x200, &[0x12, 0x34, 0x56, 0x78]).unwrap();
+        flash.read(0x200, &mut data).unwrap();
+        assert_eq!(data, [0x12, 0x34, 0, 0x78]);
+    }
+}
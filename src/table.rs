@@ -19,6 +19,18 @@ impl PartitionTable {
 
     /// Maxumum number of partition entries
     pub const MAX_ENTRIES: usize = Self::MAX_SIZE / PartitionEntry::SIZE;
+
+    /// Maximum size of binary table data like `gen_esp32part.py` produces
+    ///
+    /// The partition entries with MD5 entry should be followed by at least one
+    /// end marker entry within this size.
+    pub const MAX_DATA_SIZE: usize = 0xc00;
+
+    /// Maximum number of partition entries which can be written into table
+    ///
+    /// The entries should be followed by end marker entry within [`Self::MAX_DATA_SIZE`]
+    /// so one entry less fits when MD5 entry is written.
+    pub const MAX_DATA_ENTRIES: usize = Self::MAX_DATA_SIZE / PartitionEntry::SIZE - 1;
 }
 
 impl Default for PartitionTable {
@@ -37,6 +49,61 @@ impl PartitionTable {
     pub fn max_entries(&self) -> usize {
         self.size / PartitionEntry::SIZE
     }
+
+    /// Get size which can be occupied by partition entries including MD5 entry
+    ///
+    /// The same limit as `gen_esp32part.py` has.
+    pub fn data_size(&self) -> usize {
+        self.size
+            .min(Self::MAX_DATA_SIZE)
+            .saturating_sub(PartitionEntry::SIZE)
+    }
+
+    /// Write partitions into buffer
+    ///
+    /// The buffer is filled up to table size. Unused space is padded by `0xff`
+    /// so the result is identical to `gen_esp32part.py` output.
+    /// Returns the size of written partitions without MD5 entry.
+    ///
+    /// If `md5` feature isn't enabled `write_md5` argument will be ignored.
+    pub fn write_bytes(
+        &self,
+        data: &mut [u8],
+        partitions: impl IntoIterator<Item = impl AsRef<PartitionEntry>>,
+        write_md5: bool,
+    ) -> Result<usize, PartitionError> {
+        let size = self.size.min(data.len());
+        let table = Self::new(self.addr, size);
+        let mut state = PartitionWriterState::new(self.addr, table.data_size(), write_md5);
+        let mut count = 0;
+        let mut data = &mut data[..size];
+
+        for partition in partitions {
+            let (head, rest) = data
+                .split_first_chunk_mut()
+                .ok_or(PartitionError::TooManyData)?;
+
+            state.write(head, partition)?;
+
+            data = rest;
+            count += 1;
+        }
+
+        #[cfg(feature = "md5")]
+        if write_md5 {
+            let (head, rest) = data
+                .split_first_chunk_mut()
+                .ok_or(PartitionError::TooManyData)?;
+
+            state.write_md5(head)?;
+
+            data = rest;
+        }
+
+        data.fill(0xff);
+
+        Ok(count * PartitionEntry::SIZE)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -60,6 +127,8 @@ pub struct PartitionReaderState {
 
     stored_md5: Option<Md5Data>,
 
+    strict: bool,
+
     state: InternalState,
 }
 
@@ -83,6 +152,8 @@ impl PartitionReaderState {
 
             stored_md5: None,
 
+            strict: true,
+
             state: InternalState::Proc,
         }
     }
@@ -92,6 +163,20 @@ impl PartitionReaderState {
         self.offset
     }
 
+    /// Subtypes checking is strict
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Set strict or lenient subtypes checking
+    ///
+    /// In strict mode (default) unknown application and data subtypes cause
+    /// [`PartitionError::InvalidSubType`] error. In lenient mode such subtypes are
+    /// represented as `Other` so the partitions can be written back byte-for-byte.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
     /// Reader reached end of data
     pub fn is_done(&self) -> bool {
         matches!(self.state, InternalState::Done)
@@ -159,7 +244,11 @@ impl PartitionReaderState {
                     }
                 }
 
-                buffer.try_into()
+                if self.strict {
+                    PartitionEntry::from_bytes(buffer)
+                } else {
+                    PartitionEntry::from_bytes_lenient(buffer)
+                }
             }
             PartitionMd5::MAGIC => match buffer.try_into() {
                 Ok(PartitionMd5 { data }) => {
@@ -250,21 +339,21 @@ impl PartitionWriterState {
     }
 
     fn check(&mut self) -> Result<(), PartitionError> {
-        if self.offset >= self.end {
+        let offset = match self.state {
+            InternalState::Init => self.offset,
+            InternalState::Proc => self.offset + PartitionEntry::SIZE as u32,
+            InternalState::Done => return Err(PartitionError::TooManyData),
+        };
+
+        // Entry should entirely fit into table
+        if offset + PartitionEntry::SIZE as u32 > self.end {
             self.state = InternalState::Done;
+            return Err(PartitionError::TooManyData);
         }
 
-        match self.state {
-            InternalState::Init => {
-                self.state = InternalState::Proc;
-                Ok(())
-            }
-            InternalState::Proc => {
-                self.offset += PartitionEntry::SIZE as u32;
-                Ok(())
-            }
-            InternalState::Done => Err(PartitionError::TooManyData),
-        }
+        self.offset = offset;
+        self.state = InternalState::Proc;
+        Ok(())
     }
 
     /// Write partition data into buffer
@@ -332,7 +421,7 @@ mod test {
         assert_eq!(part.offset, 36 << 10);
         assert_eq!(part.size, 24 << 10);
         assert_eq!(part.name(), "nvs");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         let part = reader.read(part).unwrap();
@@ -340,7 +429,7 @@ mod test {
         assert_eq!(part.offset, 60 << 10);
         assert_eq!(part.size, 4 << 10);
         assert_eq!(part.name(), "phy_init");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         let part = reader.read(part).unwrap();
@@ -348,7 +437,7 @@ mod test {
         assert_eq!(part.offset, 64 << 10);
         assert_eq!(part.size, 3 << 20);
         assert_eq!(part.name(), "factory");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         let part = reader.read(part).unwrap();
@@ -356,7 +445,7 @@ mod test {
         assert_eq!(part.offset, (64 << 10) + (3 << 20));
         assert_eq!(part.size, 64 << 10);
         assert_eq!(part.name(), "coredump");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         let part = reader.read(part).unwrap();
@@ -364,7 +453,7 @@ mod test {
         assert_eq!(part.offset, (128 << 10) + (3 << 20));
         assert_eq!(part.size, 64 << 10);
         assert_eq!(part.name(), "nvs_ext");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         assert!(matches!(
@@ -394,7 +483,7 @@ mod test {
         assert_eq!(part.offset, 36 << 10);
         assert_eq!(part.size, 16 << 10);
         assert_eq!(part.name(), "nvs");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         let part = reader.read(part).unwrap();
@@ -402,7 +491,7 @@ mod test {
         assert_eq!(part.offset, 52 << 10);
         assert_eq!(part.size, 8 << 10);
         assert_eq!(part.name(), "otadata");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         let part = reader.read(part).unwrap();
@@ -410,7 +499,7 @@ mod test {
         assert_eq!(part.offset, 60 << 10);
         assert_eq!(part.size, 4 << 10);
         assert_eq!(part.name(), "phy_init");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         let part = reader.read(part).unwrap();
@@ -418,7 +507,7 @@ mod test {
         assert_eq!(part.offset, 64 << 10);
         assert_eq!(part.size, 1 << 20);
         assert_eq!(part.name(), "factory");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         let part = reader.read(part).unwrap();
@@ -426,7 +515,7 @@ mod test {
         assert_eq!(part.offset, (64 << 10) + (1 << 20));
         assert_eq!(part.size, 1 << 20);
         assert_eq!(part.name(), "ota_0");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         let part = reader.read(part).unwrap();
@@ -434,7 +523,7 @@ mod test {
         assert_eq!(part.offset, (64 << 10) + (2 << 20));
         assert_eq!(part.size, 1 << 20);
         assert_eq!(part.name(), "ota_1");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         let part = reader.read(part).unwrap();
@@ -442,7 +531,7 @@ mod test {
         assert_eq!(part.offset, (64 << 10) + (3 << 20));
         assert_eq!(part.size, 64 << 10);
         assert_eq!(part.name(), "coredump");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         let part = reader.read(part).unwrap();
@@ -450,7 +539,7 @@ mod test {
         assert_eq!(part.offset, (128 << 10) + (3 << 20));
         assert_eq!(part.size, 64 << 10);
         assert_eq!(part.name(), "nvs_ext");
-        assert!(!part.encrypted);
+        assert_eq!(part.flags, PartitionFlags::NONE);
 
         let (part, data) = data.split_first_chunk().unwrap();
         assert!(matches!(
@@ -468,6 +557,109 @@ mod test {
         ));
     }
 
+    #[test]
+    fn partition_flags() {
+        let flags = [
+            PartitionFlags::NONE,
+            PartitionFlags::ENCRYPTED,
+            PartitionFlags::READONLY,
+            PartitionFlags::from_bits(0x8000_0003),
+        ];
+        let mut table = [0xffu8; PartitionTable::MAX_SIZE];
+        let mut writer = PartitionWriterState::new(0, table.len(), false);
+
+        for (flags, data) in flags
+            .iter()
+            .zip(table.chunks_exact_mut(PartitionEntry::SIZE))
+        {
+            let part =
+                PartitionEntry::new(DataPartitionType::Nvs, 0x9000, 0x6000, "nvs", *flags).unwrap();
+            writer.write(data.try_into().unwrap(), part).unwrap();
+        }
+
+        let mut reader = PartitionReaderState::new(0, table.len(), false);
+
+        for (flags, data) in flags.iter().zip(table.chunks_exact(PartitionEntry::SIZE)) {
+            let part = reader.read(data.try_into().unwrap()).unwrap();
+            assert_eq!(&part.flags, flags);
+        }
+
+        let flags = PartitionFlags::from_bits(0x8000_0003);
+        assert!(flags.encrypted());
+        assert!(flags.readonly());
+        assert_eq!(flags.unknown_bits(), 0x8000_0000);
+    }
+
+    #[test]
+    fn unknown_subtypes() {
+        let mut table = [0xffu8; PartitionTable::MAX_SIZE];
+        let mut data = table.chunks_exact_mut(PartitionEntry::SIZE);
+        let mut writer = PartitionWriterState::new(0, PartitionTable::MAX_SIZE, false);
+
+        for part in [
+            PartitionEntry::new(DataPartitionType::Nvs, 0x9000, 0x6000, "nvs", false).unwrap(),
+            PartitionEntry::new(
+                DataPartitionType::Other(0x3f),
+                0xf000,
+                0x1000,
+                "esp_secure_cert",
+                false,
+            )
+            .unwrap(),
+            PartitionEntry::new(
+                AppPartitionType::Other(0x30),
+                0x10000,
+                1 << 20,
+                "app",
+                false,
+            )
+            .unwrap(),
+        ] {
+            writer
+                .write(data.next().unwrap().try_into().unwrap(), part)
+                .unwrap();
+        }
+
+        let mut reader = PartitionReaderState::new(0, table.len(), false);
+        assert!(reader.is_strict());
+        reader.read(table[..32].try_into().unwrap()).unwrap();
+        assert!(matches!(
+            reader.read(table[32..64].try_into().unwrap()),
+            Err(PartitionError::InvalidSubType(0x3f))
+        ));
+
+        let mut reader = PartitionReaderState::new(0, table.len(), false);
+        reader.set_strict(false);
+        let mut copy = [0xffu8; PartitionTable::MAX_SIZE];
+        let mut writer = PartitionWriterState::new(0, PartitionTable::MAX_SIZE, false);
+
+        for (src, dst) in table
+            .chunks_exact(PartitionEntry::SIZE)
+            .zip(copy.chunks_exact_mut(PartitionEntry::SIZE))
+        {
+            match reader.read(src.try_into().unwrap()) {
+                Ok(part) => writer.write(dst.try_into().unwrap(), part).unwrap(),
+                Err(PartitionError::NotEnoughData) => break,
+                Err(error) => panic!("{error}"),
+            }
+        }
+
+        assert_eq!(table, copy);
+        assert_eq!(DataPartitionType::from_raw(0x02), DataPartitionType::Nvs);
+        assert_eq!(DataPartitionType::Other(0x02), DataPartitionType::Nvs);
+        assert_ne!(
+            DataPartitionType::Other(0x3f),
+            DataPartitionType::Other(0x40)
+        );
+        assert_eq!(AppPartitionType::Other(0x15), AppPartitionType::Ota(5));
+        assert_ne!(AppPartitionType::Other(0x15), AppPartitionType::Ota(6));
+        assert_eq!(
+            PartitionType::from(AppPartitionType::Other(0x20)),
+            PartitionType::App(AppPartitionType::Test)
+        );
+        assert_eq!(DataPartitionType::Other(0x02).name(), Some("nvs"));
+    }
+
     #[test]
     fn write_partitions() {
         let src_table = include_bytes!("../tests/partitions.bin");
@@ -545,4 +737,88 @@ mod test {
 
         assert_eq!(&dst_table[..len], &src_table[..len]);
     }
+
+    #[cfg(feature = "md5")]
+    fn write_fixture(bin: &[u8]) {
+        let partitions = bin
+            .chunks_exact(PartitionEntry::SIZE)
+            .map_while(|part| PartitionEntry::try_from(part).ok());
+        let count = partitions.clone().count();
+
+        let table = PartitionTable::default();
+        let mut data = [0u8; PartitionTable::MAX_SIZE];
+        assert_eq!(
+            table.write_bytes(&mut data, partitions.clone(), true),
+            Ok(count * PartitionEntry::SIZE)
+        );
+        assert_eq!(data[..bin.len()], bin[..]);
+        assert_eq!(data[bin.len()..], [0xff; PartitionTable::MAX_SIZE - 0xc00]);
+
+        let table = PartitionTable::new(0, PartitionTable::MAX_DATA_SIZE);
+        let mut data = [0u8; PartitionTable::MAX_DATA_SIZE];
+        table.write_bytes(&mut data, partitions, true).unwrap();
+        assert_eq!(data[..], bin[..]);
+    }
+
+    #[cfg(feature = "md5")]
+    #[test]
+    fn write_partitions_fixture() {
+        write_fixture(include_bytes!("../tests/partitions.bin"));
+    }
+
+    #[cfg(feature = "md5")]
+    #[test]
+    fn write_partitions_ota_fixture() {
+        write_fixture(include_bytes!("../tests/partitions-ota.bin"));
+    }
+
+    #[test]
+    fn write_partitions_limit() {
+        let table = PartitionTable::default();
+        let partition =
+            PartitionEntry::new(DataPartitionType::Nvs, 0x9000, 0x1000, "nvs", false).unwrap();
+        let max_entries = PartitionTable::MAX_DATA_ENTRIES;
+        let mut data = [0u8; PartitionTable::MAX_SIZE];
+
+        assert_eq!(
+            table.write_bytes(
+                &mut data,
+                core::iter::repeat(&partition).take(max_entries),
+                false
+            ),
+            Ok(max_entries * PartitionEntry::SIZE)
+        );
+        assert_eq!(
+            data[max_entries * PartitionEntry::SIZE..],
+            [0xff; PartitionTable::MAX_SIZE - 0xbe0]
+        );
+        assert_eq!(
+            table.write_bytes(
+                &mut data,
+                core::iter::repeat(&partition).take(max_entries + 1),
+                false
+            ),
+            Err(PartitionError::TooManyData)
+        );
+
+        #[cfg(feature = "md5")]
+        assert_eq!(
+            table.write_bytes(
+                &mut data,
+                core::iter::repeat(&partition).take(max_entries),
+                true
+            ),
+            Err(PartitionError::TooManyData)
+        );
+
+        let table = PartitionTable::new(0, PartitionEntry::SIZE * 2);
+        assert_eq!(
+            table.write_bytes(&mut data, [&partition], false),
+            Ok(PartitionEntry::SIZE)
+        );
+        assert_eq!(
+            table.write_bytes(&mut data, [&partition; 2], false),
+            Err(PartitionError::TooManyData)
+        );
+    }
 }
@@ -1,4 +1,5 @@
 use crate::PartitionError;
+use core::fmt;
 
 /// Partition type and subtype
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
@@ -9,6 +10,12 @@ pub enum PartitionType {
     /// Data partition
     Data(DataPartitionType),
 
+    /// Bootloader partition
+    Bootloader(BootloaderPartitionType),
+
+    /// Partition table partition
+    PartitionTable(TablePartitionType),
+
     /// Any type
     #[default]
     Any,
@@ -29,13 +36,35 @@ impl From<DataPartitionType> for PartitionType {
     }
 }
 
+impl From<BootloaderPartitionType> for PartitionType {
+    fn from(subtype: BootloaderPartitionType) -> Self {
+        Self::Bootloader(subtype)
+    }
+}
+
+impl From<TablePartitionType> for PartitionType {
+    fn from(subtype: TablePartitionType) -> Self {
+        Self::PartitionTable(subtype)
+    }
+}
+
 impl TryFrom<(u8, u8)> for PartitionType {
     type Error = PartitionError;
 
     fn try_from((raw_type, raw_subtype): (u8, u8)) -> Result<Self, Self::Error> {
+        Self::from_raw(raw_type, raw_subtype, true)
+    }
+}
+
+impl PartitionType {
+    fn from_raw(raw_type: u8, raw_subtype: u8, strict: bool) -> Result<Self, PartitionError> {
         Ok(match raw_type {
+            0x00 if !strict => Self::App(AppPartitionType::from_raw(raw_subtype)),
+            0x01 if !strict => Self::Data(DataPartitionType::from_raw(raw_subtype)),
             0x00 => Self::App(raw_subtype.try_into()?),
             0x01 => Self::Data(raw_subtype.try_into()?),
+            0x02 => Self::Bootloader(raw_subtype.try_into()?),
+            0x03 => Self::PartitionTable(raw_subtype.try_into()?),
             0x40..=0xfe => Self::User(raw_type, raw_subtype),
             0xff => Self::Any,
             _ => return Err(PartitionError::InvalidType(raw_type)),
@@ -77,6 +106,8 @@ impl TryFrom<PartitionType> for (u8, u8) {
         Ok(match ty {
             App(subtype) => (0x00, subtype.try_into()?),
             Data(subtype) => (0x01, subtype.into()),
+            Bootloader(subtype) => (0x02, subtype.into()),
+            PartitionTable(subtype) => (0x03, subtype.into()),
             User(usertype @ 0x40..=0xfe, subtype) => (usertype, subtype),
             User(usertype, _) => return Err(PartitionError::InvalidUserType(usertype)),
             Any => (0xff, 0x00),
@@ -100,14 +131,30 @@ impl PartitionType {
     /// Data partition alignment
     const DATA_ALIGN: u32 = 0x1000;
 
+    /// Bootloader and partition table partition alignment
+    const SYSTEM_ALIGN: u32 = 0x1000;
+
     /// Get partition alignment
     pub fn align(&self) -> u32 {
         match self {
             PartitionType::App(_) => Self::APP_ALIGN,
+            PartitionType::Bootloader(_) | PartitionType::PartitionTable(_) => Self::SYSTEM_ALIGN,
             _ => Self::DATA_ALIGN,
         }
     }
 
+    /// Check that partition describes primary bootloader or partition table
+    ///
+    /// Such partitions are located at fixed addresses before the partition table itself
+    /// so they are not taken into account when placing other partitions.
+    pub fn is_primary(&self) -> bool {
+        matches!(
+            self,
+            PartitionType::Bootloader(BootloaderPartitionType::Primary)
+                | PartitionType::PartitionTable(TablePartitionType::Primary)
+        )
+    }
+
     /// Check offset for alignment
     pub fn check_offset(&self, offset: u32) -> Result<(), PartitionError> {
         if offset & (self.align() - 1) == 0 {
@@ -117,11 +164,48 @@ impl PartitionType {
         }
     }
 
+    /// Get partition type from name
+    ///
+    /// Only `app`, `data`, `bootloader` and `partition_table` types has names,
+    /// the name matching is case-insensitive.
+    pub fn type_from_name(name: &str) -> Option<u8> {
+        [
+            ("app", 0x00),
+            ("data", 0x01),
+            ("bootloader", 0x02),
+            ("partition_table", 0x03),
+        ]
+        .into_iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map(|(_, raw)| raw)
+    }
+
+    /// Get partition type name
+    ///
+    /// Only `app`, `data`, `bootloader` and `partition_table` types has names.
+    pub fn type_name(&self) -> Option<&'static str> {
+        match self {
+            Self::App(_) => Some("app"),
+            Self::Data(_) => Some("data"),
+            Self::Bootloader(_) => Some("bootloader"),
+            Self::PartitionTable(_) => Some("partition_table"),
+            _ => None,
+        }
+    }
+
     /// Convert type and subtype from binary representation
     pub fn from_bytes(data: &[u8; 2]) -> Result<Self, PartitionError> {
         data.try_into()
     }
 
+    /// Convert type and subtype from binary representation
+    ///
+    /// Unlike [`PartitionType::from_bytes`] unknown application and data subtypes
+    /// are represented as `Other` instead of error.
+    pub fn from_bytes_lenient(data: &[u8; 2]) -> Result<Self, PartitionError> {
+        Self::from_raw(data[0], data[1], false)
+    }
+
     /// Convert type and subtype to binary representation
     pub fn to_bytes(&self, data: &mut [u8; 2]) -> Result<(), PartitionError> {
         let (type_, subtype) = (*self).try_into()?;
@@ -132,7 +216,10 @@ impl PartitionType {
 }
 
 /// Application partition subtype
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+///
+/// The `Other` variant is intended for unknown subtypes only. When it holds a raw value
+/// of known subtype it is treated as corresponding named variant in comparisons.
+#[derive(Clone, Copy, Debug, Eq, Default)]
 pub enum AppPartitionType {
     /// Factory application
     #[default]
@@ -143,18 +230,89 @@ pub enum AppPartitionType {
 
     /// Test application
     Test,
+
+    /// Unknown application subtype
+    Other(u8),
 }
 
-impl TryFrom<u8> for AppPartitionType {
-    type Error = PartitionError;
+impl PartialEq for AppPartitionType {
+    fn eq(&self, other: &Self) -> bool {
+        use AppPartitionType::*;
+        match (self.normalize(), other.normalize()) {
+            (Factory, Factory) | (Test, Test) => true,
+            (Ota(a), Ota(b)) | (Other(a), Other(b)) => a == b,
+            _ => false,
+        }
+    }
+}
 
-    fn try_from(raw: u8) -> Result<Self, Self::Error> {
-        Ok(match raw {
+impl AppPartitionType {
+    /// Convert application subtype from raw value
+    ///
+    /// Unknown subtypes are represented as [`AppPartitionType::Other`].
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
             0x00 => Self::Factory,
             0x10..=0x1f => Self::Ota(raw - 0x10),
             0x20 => Self::Test,
-            _ => return Err(PartitionError::InvalidSubType(raw)),
-        })
+            _ => Self::Other(raw),
+        }
+    }
+
+    /// Represent known raw value held by `Other` by named variant
+    pub fn normalize(self) -> Self {
+        match self {
+            Self::Other(raw) => Self::from_raw(raw),
+            ty => ty,
+        }
+    }
+
+    /// Get application subtype from name
+    ///
+    /// The name matching is case-insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("factory") {
+            Some(Self::Factory)
+        } else if name.eq_ignore_ascii_case("test") {
+            Some(Self::Test)
+        } else {
+            let number = name
+                .get(..4)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("ota_"))
+                .and(name.get(4..))?;
+            if number.is_empty() || !number.bytes().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            match number.parse() {
+                Ok(number @ 0x00..=0x0f) => Some(Self::Ota(number)),
+                _ => None,
+            }
+        }
+    }
+}
+
+impl fmt::Display for AppPartitionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.normalize() {
+            Self::Factory => "factory".fmt(f),
+            Self::Ota(number) => {
+                "ota_".fmt(f)?;
+                number.fmt(f)
+            }
+            Self::Test => "test".fmt(f),
+            Self::Other(raw) => write!(f, "{raw:#04x}"),
+        }
+    }
+}
+
+impl TryFrom<u8> for AppPartitionType {
+    type Error = PartitionError;
+
+    fn try_from(raw: u8) -> Result<Self, Self::Error> {
+        match Self::from_raw(raw) {
+            Self::Other(raw) => Err(PartitionError::InvalidSubType(raw)),
+            ty => Ok(ty),
+        }
     }
 }
 
@@ -168,54 +326,81 @@ impl TryFrom<AppPartitionType> for u8 {
             Ota(number @ 0x00..=0x0f) => number + 0x10,
             Ota(number) => return Err(PartitionError::InvalidOtaNumber(number)),
             Test => 0x20,
+            Other(raw) => raw,
         })
     }
 }
 
 /// Data partition subtype
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
-#[repr(u8)]
+///
+/// The `Other` variant is intended for unknown subtypes only. When it holds a raw value
+/// of known subtype it is treated as corresponding named variant in comparisons.
+#[derive(Clone, Copy, Debug, Eq, Default)]
 pub enum DataPartitionType {
     /// OTA data
     #[default]
-    Ota = 0x00,
+    Ota,
 
     /// Phy data
-    Phy = 0x01,
+    Phy,
 
     /// Non-volatile storage data
-    Nvs = 0x02,
+    Nvs,
 
     /// Core dump
-    CoreDump = 0x03,
+    CoreDump,
 
     /// Encrypted non-volatile storage keys
-    NvsKeys = 0x04,
+    NvsKeys,
 
     /// Efuse data
-    EfuseEm = 0x05,
+    EfuseEm,
 
     /// Undefined data
-    Undefined = 0x06,
+    Undefined,
 
     /// ESP HTTPd data
-    EspHttpd = 0x80,
+    EspHttpd,
 
     /// FAT partition
-    Fat = 0x81,
+    Fat,
 
     /// SPIFFS partition
-    SpiFfs = 0x82,
+    SpiFfs,
 
     /// LittleFS partition
-    LittleFS = 0x83,
+    LittleFS,
+
+    /// Unknown data subtype
+    Other(u8),
 }
 
-impl TryFrom<u8> for DataPartitionType {
-    type Error = PartitionError;
+impl PartialEq for DataPartitionType {
+    fn eq(&self, other: &Self) -> bool {
+        u8::from(*self) == u8::from(*other)
+    }
+}
 
-    fn try_from(raw: u8) -> Result<Self, Self::Error> {
-        Ok(match raw {
+impl DataPartitionType {
+    const NAMES: [(&'static str, Self); 11] = [
+        ("ota", Self::Ota),
+        ("phy", Self::Phy),
+        ("nvs", Self::Nvs),
+        ("coredump", Self::CoreDump),
+        ("nvs_keys", Self::NvsKeys),
+        ("efuse", Self::EfuseEm),
+        ("undefined", Self::Undefined),
+        ("esphttpd", Self::EspHttpd),
+        ("fat", Self::Fat),
+        ("spiffs", Self::SpiFfs),
+        ("littlefs", Self::LittleFS),
+    ];
+
+    /// Convert data subtype from raw value
+    ///
+    /// Unknown subtypes are represented as [`DataPartitionType::Other`].
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
             0x00 => Self::Ota,
             0x01 => Self::Phy,
             0x02 => Self::Nvs,
@@ -227,13 +412,201 @@ impl TryFrom<u8> for DataPartitionType {
             0x81 => Self::Fat,
             0x82 => Self::SpiFfs,
             0x83 => Self::LittleFS,
-            _ => return Err(PartitionError::InvalidSubType(raw)),
-        })
+            _ => Self::Other(raw),
+        }
+    }
+
+    /// Represent known raw value held by `Other` by named variant
+    pub fn normalize(self) -> Self {
+        match self {
+            Self::Other(raw) => Self::from_raw(raw),
+            ty => ty,
+        }
+    }
+
+    /// Get data subtype from name
+    ///
+    /// The name matching is case-insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, ty)| *ty)
+    }
+
+    /// Get data subtype name
+    ///
+    /// Unknown subtypes has no names.
+    pub fn name(&self) -> Option<&'static str> {
+        Self::NAMES
+            .iter()
+            .find(|(_, ty)| ty == self)
+            .map(|(name, _)| *name)
+    }
+}
+
+impl fmt::Display for DataPartitionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => name.fmt(f),
+            None => write!(f, "{:#04x}", u8::from(*self)),
+        }
+    }
+}
+
+impl TryFrom<u8> for DataPartitionType {
+    type Error = PartitionError;
+
+    fn try_from(raw: u8) -> Result<Self, Self::Error> {
+        match Self::from_raw(raw) {
+            Self::Other(raw) => Err(PartitionError::InvalidSubType(raw)),
+            ty => Ok(ty),
+        }
     }
 }
 
 impl From<DataPartitionType> for u8 {
     fn from(ty: DataPartitionType) -> Self {
+        use DataPartitionType::*;
+        match ty {
+            Ota => 0x00,
+            Phy => 0x01,
+            Nvs => 0x02,
+            CoreDump => 0x03,
+            NvsKeys => 0x04,
+            EfuseEm => 0x05,
+            Undefined => 0x06,
+            EspHttpd => 0x80,
+            Fat => 0x81,
+            SpiFfs => 0x82,
+            LittleFS => 0x83,
+            Other(raw) => raw,
+        }
+    }
+}
+
+/// Bootloader partition subtype
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum BootloaderPartitionType {
+    /// Primary bootloader
+    #[default]
+    Primary = 0x00,
+
+    /// OTA bootloader
+    Ota = 0x01,
+
+    /// Recovery bootloader
+    Recovery = 0x02,
+}
+
+impl BootloaderPartitionType {
+    const NAMES: [(&'static str, Self); 3] = [
+        ("primary", Self::Primary),
+        ("ota", Self::Ota),
+        ("recovery", Self::Recovery),
+    ];
+
+    /// Get bootloader subtype from name
+    ///
+    /// The name matching is case-insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, ty)| *ty)
+    }
+
+    /// Get bootloader subtype name
+    pub fn name(&self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find(|(_, ty)| ty == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for BootloaderPartitionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+impl TryFrom<u8> for BootloaderPartitionType {
+    type Error = PartitionError;
+
+    fn try_from(raw: u8) -> Result<Self, Self::Error> {
+        Ok(match raw {
+            0x00 => Self::Primary,
+            0x01 => Self::Ota,
+            0x02 => Self::Recovery,
+            _ => return Err(PartitionError::InvalidSubType(raw)),
+        })
+    }
+}
+
+impl From<BootloaderPartitionType> for u8 {
+    fn from(ty: BootloaderPartitionType) -> Self {
+        ty as _
+    }
+}
+
+/// Partition table partition subtype
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum TablePartitionType {
+    /// Primary partition table
+    #[default]
+    Primary = 0x00,
+
+    /// OTA partition table
+    Ota = 0x01,
+}
+
+impl TablePartitionType {
+    const NAMES: [(&'static str, Self); 2] = [("primary", Self::Primary), ("ota", Self::Ota)];
+
+    /// Get partition table subtype from name
+    ///
+    /// The name matching is case-insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, ty)| *ty)
+    }
+
+    /// Get partition table subtype name
+    pub fn name(&self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find(|(_, ty)| ty == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for TablePartitionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+impl TryFrom<u8> for TablePartitionType {
+    type Error = PartitionError;
+
+    fn try_from(raw: u8) -> Result<Self, Self::Error> {
+        Ok(match raw {
+            0x00 => Self::Primary,
+            0x01 => Self::Ota,
+            _ => return Err(PartitionError::InvalidSubType(raw)),
+        })
+    }
+}
+
+impl From<TablePartitionType> for u8 {
+    fn from(ty: TablePartitionType) -> Self {
         ty as _
     }
 }
@@ -9,6 +9,28 @@ pub fn name_from(data: &[u8; PartitionEntry::MAX_NAME_LEN]) -> Result<&str, Part
     str::from_utf8(name_trim(data)).map_err(|_| PartitionError::InvalidString)
 }
 
+/// Get string up to the first NUL or invalid UTF-8 character
+pub fn str_lossy(data: &[u8]) -> &str {
+    let data = data.split(|c| *c == b'\0').next().unwrap_or(data);
+    str::from_utf8(data).unwrap_or_else(|error| {
+        // Valid part of string always converts successfully
+        str::from_utf8(&data[..error.valid_up_to()]).unwrap_or_default()
+    })
+}
+
+/// Strip leading and trailing ASCII whitespace
+pub fn trim_ascii(data: &[u8]) -> &[u8] {
+    let start = data
+        .iter()
+        .position(|c| !c.is_ascii_whitespace())
+        .unwrap_or(data.len());
+    let end = data
+        .iter()
+        .rposition(|c| !c.is_ascii_whitespace())
+        .map_or(start, |end| end + 1);
+    &data[start..end]
+}
+
 pub fn name_into(
     data: &mut [u8; PartitionEntry::MAX_NAME_LEN],
     name: &str,
@@ -22,3 +44,16 @@ pub fn name_into(
     tail.fill(0);
     Ok(())
 }
+
+/// Compute CRC32 like `esp_rom_crc32_le` does
+pub fn crc32_le(crc: u32, data: &[u8]) -> u32 {
+    !data.iter().fold(!crc, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
@@ -0,0 +1,338 @@
+use crate::{PartitionEntry, PartitionTable, TablePartitionType};
+use core::{fmt, iter::Enumerate};
+
+/// Partition table violation kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// Partition offset is not properly aligned
+    Misaligned,
+
+    /// Partition overlaps partition table region
+    OverlapsTable,
+
+    /// Partition runs past the end of flash
+    OutOfFlash,
+
+    /// Partition overlaps previous partition with specified index
+    Overlaps(usize),
+
+    /// Partition has the same name as previous partition with specified index
+    DuplicateName(usize),
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ViolationKind::*;
+        match self {
+            Misaligned => "Misaligned offset".fmt(f),
+            OverlapsTable => "Overlaps partition table".fmt(f),
+            OutOfFlash => "Runs past the end of flash".fmt(f),
+            Overlaps(index) => {
+                "Overlaps partition #".fmt(f)?;
+                index.fmt(f)
+            }
+            DuplicateName(index) => {
+                "Same name as partition #".fmt(f)?;
+                index.fmt(f)
+            }
+        }
+    }
+}
+
+/// Partition table violation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartitionViolation {
+    /// Index of partition entry
+    pub index: usize,
+
+    /// Violation kind
+    pub kind: ViolationKind,
+}
+
+impl fmt::Display for PartitionViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "Partition #".fmt(f)?;
+        self.index.fmt(f)?;
+        ": ".fmt(f)?;
+        self.kind.fmt(f)
+    }
+}
+
+impl PartitionTable {
+    /// Validate partitions layout
+    ///
+    /// Returns iterator over all found violations. Partitions are checked for alignment,
+    /// overlapping with partition table (except the primary partition table entry itself),
+    /// fitting into flash of `flash_size` bytes, overlapping with each other and duplicated names.
+    pub fn validate<I>(
+        &self,
+        partitions: I,
+        flash_size: usize,
+    ) -> PartitionViolationIter<I::IntoIter>
+    where
+        I: IntoIterator,
+        I::IntoIter: Clone,
+        I::Item: AsRef<PartitionEntry>,
+    {
+        let partitions = partitions.into_iter();
+
+        PartitionViolationIter {
+            table: *self,
+            flash_size,
+            outer: partitions.clone().enumerate(),
+            inner: partitions.clone().enumerate(),
+            partitions,
+            current: None,
+            check: Check::Align,
+            pending: None,
+        }
+    }
+
+    /// Check partitions layout
+    ///
+    /// Returns the first found violation.
+    pub fn check<I>(&self, partitions: I, flash_size: usize) -> Result<(), PartitionViolation>
+    where
+        I: IntoIterator,
+        I::IntoIter: Clone,
+        I::Item: AsRef<PartitionEntry>,
+    {
+        self.validate(partitions, flash_size)
+            .next()
+            .map_or(Ok(()), Err)
+    }
+
+    /// Check layout of partitions encoded into table data
+    ///
+    /// Allows validating partitions from one-shot iterators after writing them into buffer.
+    #[cfg(feature = "embedded-storage")]
+    pub(crate) fn check_bytes(
+        &self,
+        data: &[u8],
+        flash_size: usize,
+    ) -> Result<(), crate::PartitionError> {
+        let partitions = data
+            .chunks_exact(PartitionEntry::SIZE)
+            .map_while(|data| PartitionEntry::from_bytes_lenient(data.try_into().ok()?).ok());
+
+        self.check(partitions, flash_size)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Check {
+    Align,
+    Table,
+    Flash,
+    Other,
+}
+
+/// Iterator over partition table violations
+pub struct PartitionViolationIter<I: Iterator> {
+    table: PartitionTable,
+    flash_size: usize,
+    partitions: I,
+    outer: Enumerate<I>,
+    inner: Enumerate<I>,
+    current: Option<(usize, I::Item)>,
+    check: Check,
+    pending: Option<PartitionViolation>,
+}
+
+pub(crate) fn overlaps(offset_a: u32, size_a: usize, offset_b: u32, size_b: usize) -> bool {
+    let (offset_a, offset_b) = (offset_a as u64, offset_b as u64);
+    offset_a < offset_b + size_b as u64 && offset_b < offset_a + size_a as u64
+}
+
+impl<I> Iterator for PartitionViolationIter<I>
+where
+    I: Iterator + Clone,
+    I::Item: AsRef<PartitionEntry>,
+{
+    type Item = PartitionViolation;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(violation) = self.pending.take() {
+            return Some(violation);
+        }
+
+        loop {
+            if self.current.is_none() {
+                self.current = Some(self.outer.next()?);
+                self.inner = self.partitions.clone().enumerate();
+                self.check = Check::Align;
+            }
+
+            let (index, partition) = self.current.as_ref().unwrap();
+            let (index, partition) = (*index, partition.as_ref());
+            let violation = |kind| Some(PartitionViolation { index, kind });
+
+            match self.check {
+                Check::Align => {
+                    self.check = Check::Table;
+                    if partition.type_.check_offset(partition.offset).is_err() {
+                        return violation(ViolationKind::Misaligned);
+                    }
+                }
+                Check::Table => {
+                    self.check = Check::Flash;
+                    // Primary partition table entry describes the table itself
+                    if partition.type_ != TablePartitionType::Primary.into()
+                        && overlaps(
+                            partition.offset,
+                            partition.size,
+                            self.table.addr,
+                            self.table.size,
+                        )
+                    {
+                        return violation(ViolationKind::OverlapsTable);
+                    }
+                }
+                Check::Flash => {
+                    self.check = Check::Other;
+                    if partition.offset as u64 + partition.size as u64 > self.flash_size as u64 {
+                        return violation(ViolationKind::OutOfFlash);
+                    }
+                }
+                Check::Other => {
+                    let Some((other_index, other)) = self
+                        .inner
+                        .next()
+                        .filter(|(other_index, _)| *other_index < index)
+                    else {
+                        self.current = None;
+                        continue;
+                    };
+                    let other = other.as_ref();
+
+                    let overlap =
+                        overlaps(partition.offset, partition.size, other.offset, other.size)
+                            .then_some(ViolationKind::Overlaps(other_index));
+                    let duplicate = (partition.name() == other.name())
+                        .then_some(ViolationKind::DuplicateName(other_index));
+
+                    match (overlap, duplicate) {
+                        (Some(overlap), Some(duplicate)) => {
+                            self.pending = Some(PartitionViolation {
+                                index,
+                                kind: duplicate,
+                            });
+                            return violation(overlap);
+                        }
+                        (Some(kind), None) | (None, Some(kind)) => return violation(kind),
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    const FLASH_SIZE: usize = 4 << 20;
+
+    fn violations<const N: usize>(
+        partitions: &[PartitionEntry],
+    ) -> [Option<PartitionViolation>; N] {
+        let mut iter = PartitionTable::default().validate(partitions, FLASH_SIZE);
+        let result = core::array::from_fn(|_| iter.next());
+        assert_eq!(iter.next(), None);
+        result
+    }
+
+    #[test]
+    fn valid_partitions() {
+        let table = PartitionTable::default();
+
+        let partitions = table
+            .iter_csv(include_str!("../tests/partitions-ota.csv"))
+            .map(Result::unwrap);
+        assert_eq!(table.validate(partitions, FLASH_SIZE).next(), None);
+
+        let partitions = [
+            PartitionEntry::new(DataPartitionType::Nvs, 0x9000, 0x6000, "nvs", false).unwrap(),
+            PartitionEntry::new(DataPartitionType::Phy, 0xf000, 0x1000, "phy_init", false).unwrap(),
+            PartitionEntry::new(
+                AppPartitionType::Factory,
+                0x10000,
+                3 << 20,
+                "factory",
+                false,
+            )
+            .unwrap(),
+        ];
+
+        assert_eq!(table.validate(&partitions, FLASH_SIZE).next(), None);
+        assert_eq!(table.check(&partitions, FLASH_SIZE), Ok(()));
+    }
+
+    #[test]
+    fn invalid_partitions() {
+        let partitions = [
+            PartitionEntry::new(DataPartitionType::Nvs, 0x7000, 0x6000, "nvs", false).unwrap(),
+            PartitionEntry::new(DataPartitionType::Phy, 0xf000, 0x1000, "phy_init", false).unwrap(),
+            PartitionEntry::new(
+                AppPartitionType::Factory,
+                0x10000,
+                3 << 20,
+                "factory",
+                false,
+            )
+            .unwrap(),
+            PartitionEntry::new(DataPartitionType::Nvs, 0xc000, 0x4000, "nvs", false).unwrap(),
+            PartitionEntry::new(AppPartitionType::Ota(0), 0x311000, 0x80000, "ota_0", false)
+                .unwrap(),
+        ];
+
+        assert_eq!(
+            violations::<5>(&partitions),
+            [
+                Some(PartitionViolation {
+                    index: 0,
+                    kind: ViolationKind::OverlapsTable,
+                }),
+                Some(PartitionViolation {
+                    index: 3,
+                    kind: ViolationKind::Overlaps(0),
+                }),
+                Some(PartitionViolation {
+                    index: 3,
+                    kind: ViolationKind::DuplicateName(0),
+                }),
+                Some(PartitionViolation {
+                    index: 3,
+                    kind: ViolationKind::Overlaps(1),
+                }),
+                Some(PartitionViolation {
+                    index: 4,
+                    kind: ViolationKind::Misaligned,
+                }),
+            ]
+        );
+
+        let partitions = [
+            PartitionEntry::new(
+                AppPartitionType::Factory,
+                0x10000,
+                3 << 20,
+                "factory",
+                false,
+            )
+            .unwrap(),
+            PartitionEntry::new(AppPartitionType::Ota(0), 0x310000, 1 << 20, "ota_0", false)
+                .unwrap(),
+        ];
+
+        assert_eq!(
+            violations::<1>(&partitions),
+            [Some(PartitionViolation {
+                index: 1,
+                kind: ViolationKind::OutOfFlash,
+            })]
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use crate::{
+    utils, Md5Data, PartitionBuffer, PartitionEntry, PartitionError, PartitionFlags, PartitionMd5,
+    PartitionType,
+};
+use core::{fmt, slice::ChunksExact};
+
+/// Borrowed view over binary partition table
+///
+/// Useful when the whole table is already in memory (for example memory-mapped flash).
+/// Entries are not copied but decoded on access.
+#[derive(Clone, Copy, Debug)]
+pub struct PartitionTableView<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> PartitionTableView<'a> {
+    /// Create view over table data
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Get underlying data
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Iterate over partition entries
+    ///
+    /// The MD5 checksum is computed while iterating.
+    pub fn iter(&self) -> PartitionViewIter<'a> {
+        PartitionViewIter {
+            chunks: self.data.chunks_exact(PartitionEntry::SIZE),
+            done: false,
+            stored_md5: None,
+            #[cfg(feature = "md5")]
+            md5: Err(md5::Context::new()),
+        }
+    }
+
+    /// Iterate over all partition entries
+    fn walk(&self) -> PartitionViewIter<'a> {
+        let mut iter = self.iter();
+        iter.by_ref().for_each(drop);
+        iter
+    }
+
+    /// Get number of partition entries
+    pub fn len(&self) -> usize {
+        self.data
+            .chunks_exact(PartitionEntry::SIZE)
+            .take_while(|data| data.starts_with(&PartitionEntry::MAGIC))
+            .count()
+    }
+
+    /// Table has no partition entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get partition entry by index
+    pub fn get(&self, index: usize) -> Option<PartitionEntryView<'a>> {
+        self.iter().nth(index)?.ok()
+    }
+
+    /// Find partition entry by name
+    pub fn find(&self, name: &str) -> Option<PartitionEntryView<'a>> {
+        self.iter()
+            .map_while(Result::ok)
+            .find(|partition| partition.name() == Ok(name))
+    }
+
+    /// Get stored MD5 checksum
+    ///
+    /// Returns `None` when no checksum entry follows partition entries.
+    pub fn stored_md5(&self) -> Option<Md5Data> {
+        self.walk().stored_md5().copied()
+    }
+
+    /// Compute MD5 checksum of partition entries
+    ///
+    /// Returns `None` when some entry is not a valid or `md5` feature isn't enabled.
+    pub fn actual_md5(&self) -> Option<Md5Data> {
+        self.walk().actual_md5().copied()
+    }
+
+    /// Check partition table consistency
+    ///
+    /// Returns `None` when checksum isn't stored or `md5` feature isn't enabled.
+    /// The entries are walked only once.
+    pub fn check_md5(&self) -> Option<bool> {
+        self.walk().check_md5()
+    }
+}
+
+impl<'a> IntoIterator for PartitionTableView<'a> {
+    type Item = Result<PartitionEntryView<'a>, PartitionError>;
+    type IntoIter = PartitionViewIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &PartitionTableView<'a> {
+    type Item = Result<PartitionEntryView<'a>, PartitionError>;
+    type IntoIter = PartitionViewIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over partition entries of borrowed table
+///
+/// Stops at the MD5 entry or erased data.
+/// The entry with invalid magic causes an error and stops iteration.
+/// The MD5 checksum of entries is computed on the fly like [`crate::PartitionReaderState`] does.
+#[derive(Clone)]
+pub struct PartitionViewIter<'a> {
+    chunks: ChunksExact<'a, u8>,
+    done: bool,
+    stored_md5: Option<Md5Data>,
+    #[cfg(feature = "md5")]
+    md5: Result<Md5Data, md5::Context>,
+}
+
+impl PartitionViewIter<'_> {
+    /// Iterator reached end of entries
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Get stored MD5 checksum
+    ///
+    /// Available when iterator reached MD5 entry.
+    pub fn stored_md5(&self) -> Option<&Md5Data> {
+        self.stored_md5.as_ref()
+    }
+
+    /// Get computed MD5 checksum
+    ///
+    /// Available when iterator reached end of entries without errors.
+    pub fn actual_md5(&self) -> Option<&Md5Data> {
+        #[cfg(feature = "md5")]
+        {
+            self.md5.as_ref().ok()
+        }
+
+        #[cfg(not(feature = "md5"))]
+        {
+            None
+        }
+    }
+
+    /// Check partition table consistency
+    pub fn check_md5(&self) -> Option<bool> {
+        let stored_md5 = self.stored_md5()?;
+        self.actual_md5().map(|actual_md5| actual_md5 == stored_md5)
+    }
+
+    fn finish(&mut self) {
+        self.done = true;
+
+        #[cfg(feature = "md5")]
+        if let Err(ctx) = &self.md5 {
+            self.md5 = Ok(ctx.clone().compute().into());
+        }
+    }
+}
+
+impl fmt::Debug for PartitionViewIter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PartitionViewIter")
+            .field("chunks", &self.chunks)
+            .field("done", &self.done)
+            .field("stored_md5", &self.stored_md5)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> Iterator for PartitionViewIter<'a> {
+    type Item = Result<PartitionEntryView<'a>, PartitionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let Some(data) = self.chunks.next().and_then(|data| data.try_into().ok()) else {
+            self.finish();
+            return None;
+        };
+
+        match PartitionEntryView::new(data) {
+            Ok(view) => {
+                #[cfg(feature = "md5")]
+                if let Err(ctx) = &mut self.md5 {
+                    ctx.consume(data);
+                }
+                Some(Ok(view))
+            }
+            Err(_) if data.starts_with(&PartitionMd5::MAGIC) => {
+                self.stored_md5 = PartitionMd5::from_bytes(data).ok().map(Md5Data::from);
+                self.finish();
+                None
+            }
+            Err(_) if data.starts_with(&[0xff, 0xff]) => {
+                self.finish();
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Borrowed view over binary partition entry
+///
+/// Fields are decoded on access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartitionEntryView<'a> {
+    data: &'a PartitionBuffer,
+}
+
+impl<'a> PartitionEntryView<'a> {
+    /// Create view over entry data
+    ///
+    /// Only magic bytes are checked.
+    pub fn new(data: &'a PartitionBuffer) -> Result<Self, PartitionError> {
+        if !data.starts_with(&PartitionEntry::MAGIC) {
+            return Err(PartitionError::InvalidMagic);
+        }
+        Ok(Self { data })
+    }
+
+    /// Get underlying data
+    pub fn data(&self) -> &'a PartitionBuffer {
+        self.data
+    }
+
+    /// Get partition type and subtype
+    pub fn type_(&self) -> Result<PartitionType, PartitionError> {
+        PartitionType::from_bytes(self.field::<2, 2>())
+    }
+
+    /// Get partition type and subtype
+    ///
+    /// Unknown application and data subtypes are represented as `Other`.
+    pub fn type_lenient(&self) -> Result<PartitionType, PartitionError> {
+        PartitionType::from_bytes_lenient(self.field::<2, 2>())
+    }
+
+    /// Get partition offset
+    pub fn offset(&self) -> u32 {
+        u32::from_le_bytes(*self.field::<4, 4>())
+    }
+
+    /// Get partition size
+    pub fn size(&self) -> usize {
+        u32::from_le_bytes(*self.field::<8, 4>()) as usize
+    }
+
+    /// Get partition name
+    pub fn name(&self) -> Result<&'a str, PartitionError> {
+        utils::name_from(self.field::<12, { PartitionEntry::MAX_NAME_LEN }>())
+    }
+
+    /// Get partition flags
+    pub fn flags(&self) -> PartitionFlags {
+        u32::from_le_bytes(*self.field::<28, 4>()).into()
+    }
+
+    /// Decode owned partition entry
+    pub fn to_entry(&self) -> Result<PartitionEntry, PartitionError> {
+        PartitionEntry::from_bytes(self.data)
+    }
+
+    fn field<const OFFSET: usize, const LENGTH: usize>(&self) -> &'a [u8; LENGTH] {
+        self.data[OFFSET..][..LENGTH].try_into().unwrap()
+    }
+}
+
+impl<'a> TryFrom<&'a PartitionBuffer> for PartitionEntryView<'a> {
+    type Error = PartitionError;
+
+    fn try_from(data: &'a PartitionBuffer) -> Result<Self, Self::Error> {
+        Self::new(data)
+    }
+}
+
+impl TryFrom<PartitionEntryView<'_>> for PartitionEntry {
+    type Error = PartitionError;
+
+    fn try_from(view: PartitionEntryView<'_>) -> Result<Self, Self::Error> {
+        view.to_entry()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    const BIN: &[u8] = include_bytes!("../tests/partitions-ota.bin");
+
+    #[test]
+    fn table_view() {
+        let view = PartitionTableView::new(BIN);
+        assert_eq!(view.len(), 8);
+        assert!(!view.is_empty());
+
+        let mut state = PartitionReaderState::new(0, BIN.len(), true);
+        let mut count = 0;
+        for (partition, data) in view.iter().zip(BIN.chunks_exact(PartitionEntry::SIZE)) {
+            let partition = partition.unwrap();
+            let entry = state.read(data.try_into().unwrap()).unwrap();
+            assert_eq!(partition.type_(), Ok(entry.type_));
+            assert_eq!(partition.offset(), entry.offset);
+            assert_eq!(partition.size(), entry.size);
+            assert_eq!(partition.name(), Ok(entry.name()));
+            assert_eq!(partition.flags(), entry.flags);
+            assert_eq!(partition.to_entry(), Ok(entry));
+            count += 1;
+        }
+        assert_eq!(count, 8);
+        assert_eq!(
+            state.read(BIN[8 * PartitionEntry::SIZE..].first_chunk().unwrap()),
+            Err(PartitionError::NotEnoughData)
+        );
+
+        let otadata = view.find("otadata").unwrap();
+        assert_eq!(otadata.offset(), 0xd000);
+        assert_eq!(otadata.type_(), Ok(DataPartitionType::Ota.into()));
+        assert_eq!(view.get(1), Some(otadata));
+        assert!(view.find("unknown").is_none());
+        assert!(view.get(8).is_none());
+
+        assert!(view.stored_md5().is_some());
+        #[cfg(feature = "md5")]
+        {
+            assert_eq!(view.actual_md5().as_ref(), state.actual_md5());
+            assert_eq!(view.check_md5(), Some(true));
+        }
+
+        let mut data = [0; 0x120];
+        data.copy_from_slice(&BIN[..0x120]);
+        data[0x10] ^= 1;
+        let view = PartitionTableView::new(&data);
+        #[cfg(feature = "md5")]
+        assert_eq!(view.check_md5(), Some(false));
+        let mut iter = view.iter();
+        assert_eq!(iter.by_ref().count(), 8);
+        assert!(iter.is_done());
+        assert_eq!(iter.stored_md5(), state.stored_md5());
+        #[cfg(feature = "md5")]
+        assert_eq!(iter.check_md5(), Some(false));
+
+        data[0x20] = 0;
+        let view = PartitionTableView::new(&data);
+        assert_eq!(view.len(), 1);
+        assert_eq!(view.stored_md5(), None);
+        assert_eq!(view.check_md5(), None);
+        let mut iter = view.iter();
+        assert!(iter.next().unwrap().is_ok());
+        assert_eq!(iter.next(), Some(Err(PartitionError::InvalidMagic)));
+        assert_eq!(iter.next(), None);
+
+        assert!(PartitionTableView::new(&[0xff; 0x40]).is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+#!/usr/bin/env python3
+"""Generate `app.bin` test image
+
+The image follows ESP-IDF application image format (`esp_app_format.h` and
+`esp_image_format.h`) like `esptool.py elf2image` produces it for ESP32:
+
+- image header with two segments and SHA-256 digest appended
+- DROM segment which starts with application description
+- IRAM segment with some code-like data
+- padding and checksum byte aligned to 16 bytes
+- SHA-256 digest of all preceding data
+
+Usage: gen_app.py [output]
+"""
+
+import hashlib
+import struct
+import sys
+
+ESP_IMAGE_HEADER_MAGIC = 0xE9
+ESP_APP_DESC_MAGIC_WORD = 0xABCD5432
+ESP_CHECKSUM_MAGIC = 0xEF
+
+
+def image_header(segment_count, entry_addr):
+    return struct.pack(
+        "<BBBBIB3sHBHH4sB",
+        ESP_IMAGE_HEADER_MAGIC,
+        segment_count,
+        2,  # spi_mode: DIO
+        0x20,  # spi_speed: 40M (low nibble), spi_size: 4MB (high nibble)
+        entry_addr,
+        0xEE,  # wp_pin: disabled
+        bytes(3),  # spi_pin_drv
+        0x0000,  # chip_id: ESP32
+        0,  # min_chip_rev
+        0,  # min_chip_rev_full
+        399,  # max_chip_rev_full: v3.99
+        bytes(4),  # reserved
+        1,  # hash_appended
+    )
+
+
+def app_desc():
+    return struct.pack(
+        "<II8s32s32s16s16s32s32sHHB3s72s",
+        ESP_APP_DESC_MAGIC_WORD,
+        1,  # secure_version
+        bytes(8),  # reserv1
+        b"1.2.3",  # version
+        b"hello_world",  # project_name
+        b"12:34:56",  # time
+        b"Oct 16 2026",  # date
+        b"v5.3.1",  # idf_ver
+        bytes(range(32)),  # app_elf_sha256
+        0,  # min_efuse_blk_rev_full
+        99,  # max_efuse_blk_rev_full: v0.99
+        16,  # mmu_page_size: log2 of 64K
+        bytes(3),  # reserv3
+        bytes(72),  # reserv2
+    )
+
+
+def segment(load_addr, data):
+    return struct.pack("<II", load_addr, len(data)) + data
+
+
+def image():
+    segments = [
+        (0x3F400020, app_desc() + b"DROM data".ljust(12, b"\0")),
+        (0x40080000, bytes(index * 7 % 256 for index in range(64))),
+    ]
+
+    data = image_header(len(segments), 0x400810A0)
+    checksum = ESP_CHECKSUM_MAGIC
+    for load_addr, payload in segments:
+        data += segment(load_addr, payload)
+        for byte in payload:
+            checksum ^= byte
+
+    # Checksum is the last byte of 16-byte aligned block
+    data += bytes(15 - len(data) % 16) + bytes([checksum])
+    return data + hashlib.sha256(data).digest()
+
+
+if __name__ == "__main__":
+    output = sys.argv[1] if len(sys.argv) > 1 else "app.bin"
+    with open(output, "wb") as file:
+        file.write(image())